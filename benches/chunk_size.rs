@@ -0,0 +1,80 @@
+//! Effect of the field ID type in the internal fields list on sorting, run with
+//! `cargo bench --bench chunk_size`.
+//!
+//! Field IDs used to be stored as `u128`, this compares sorting fields lists with `u32` and
+//! `u128` IDs, besides the throughput of sorting a 1000-field message.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use protofixer::sort_protobuf_message;
+use std::mem::size_of;
+
+const FIELD_COUNT: usize = 1000;
+
+/// Same layout as the internal parsed field, with the given field ID type
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct Chunk<Id> {
+    id: Id,
+    wire_type: u8,
+    offset: usize,
+    header_length: usize,
+    length: usize,
+}
+
+/// Field IDs in pseudo-random order
+fn field_ids() -> impl Iterator<Item = u32> {
+    (0..FIELD_COUNT as u32).map(|i| i.wrapping_mul(7919) % 2000 + 1)
+}
+
+/// Message with 1000 varint fields in pseudo-random field ID order
+fn unsorted_message() -> Vec<u8> {
+    let mut msg = Vec::new();
+    for field_id in field_ids() {
+        let mut tag = field_id << 3;
+        while tag >= 0x80 {
+            msg.push(tag as u8 | 0x80);
+            tag >>= 7;
+        }
+        msg.push(tag as u8);
+        msg.push(0x01);
+    }
+    msg
+}
+
+fn chunks<Id: From<u32>>() -> Vec<Chunk<Id>> {
+    let chunks = field_ids().enumerate().map(|(i, field_id)| Chunk {
+        id: Id::from(field_id),
+        wire_type: 0,
+        offset: i * 3,
+        header_length: 2,
+        length: 3,
+    });
+    chunks.collect()
+}
+
+fn bench_sort_message(c: &mut Criterion) {
+    let msg = unsorted_message();
+    let mut group = c.benchmark_group("chunk_size");
+    group.throughput(Throughput::Bytes(msg.len() as u64));
+    group.bench_function(format!("sort_protobuf_message/{}", FIELD_COUNT), |b| {
+        b.iter(|| sort_protobuf_message(black_box(&msg)))
+    });
+    group.finish();
+}
+
+fn bench_sort_chunks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("chunk_size");
+    let (chunks_u32, chunks_u128) = (chunks::<u32>(), chunks::<u128>());
+    group.throughput(Throughput::Bytes((FIELD_COUNT * size_of::<Chunk<u32>>()) as u64));
+    group.bench_function(format!("sort_chunks/u32/{}", FIELD_COUNT), |b| {
+        b.iter(|| black_box(chunks_u32.clone()).sort_by_key(|ck| ck.id))
+    });
+    group.throughput(Throughput::Bytes((FIELD_COUNT * size_of::<Chunk<u128>>()) as u64));
+    group.bench_function(format!("sort_chunks/u128/{}", FIELD_COUNT), |b| {
+        b.iter(|| black_box(chunks_u128.clone()).sort_by_key(|ck| ck.id))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_sort_message, bench_sort_chunks);
+criterion_main!(benches);
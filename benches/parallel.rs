@@ -0,0 +1,39 @@
+//! Batch sort scaling benchmark, run with `cargo bench --bench parallel --features rayon`.
+//!
+//! Each message is sorted independently, so throughput should grow nearly linearly
+//! with the number of threads, up to the number of cores.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use protofixer::sort_multiple_parallel;
+
+const MESSAGE_COUNT: usize = 1000;
+
+/// Message of `count` varint fields in reverse field ID order
+fn reversed_message(count: u8) -> Vec<u8> {
+    (1..=count)
+        .rev()
+        .flat_map(|field_id| [field_id << 3, field_id])
+        .collect()
+}
+
+fn bench_sort_multiple_parallel(c: &mut Criterion) {
+    let messages = vec![reversed_message(15); MESSAGE_COUNT];
+    let mut group = c.benchmark_group("sort_multiple_parallel");
+    group.throughput(Throughput::Elements(MESSAGE_COUNT as u64));
+    let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let thread_counts = std::iter::successors(Some(1), |n| Some(n * 2)).take_while(|&n| n <= max_threads);
+    for threads in thread_counts {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &messages, |b, messages| {
+            b.iter_batched(
+                || messages.clone(),
+                |messages| pool.install(|| sort_multiple_parallel(messages)),
+                criterion::BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sort_multiple_parallel);
+criterion_main!(benches);
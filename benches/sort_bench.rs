@@ -0,0 +1,89 @@
+//! Sort throughput benchmark, run with `cargo bench --bench sort_bench --features bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use protofixer::{
+    _bench_sort_message_internal, sort_protobuf_message, sort_protobuf_message_with_options, SortOptions,
+};
+
+const CANONICAL_FIELD_ORDER: &str = concat!(
+    "08541a206519a0dd8255be656014fc1e89efad6871a111bc0837ec13b886c94b",
+    "d08cf41a22220a203cc289d22a301557d04e3e88b76b1299785a1dee92c1ccb2",
+    "334dc86c98501bc1280130904e38904e40f7b7df81923048f787b2b097305204",
+    "10e0a71258046a41c95509f78a317a01e39c9fbf5a7541f04b47181ac46a3e12",
+    "a31c0489d14bddd54cb71b13554b1acae37ffecc936bd448db604d2796a94c81",
+    "2482133e343a9d0e1b7002"
+);
+
+const NON_CANONICAL_FIELD_ORDER: &str = concat!(
+    "08546a41c95509f78a317a01e39c9fbf5a7541f04b47181ac46a3e12a31c0489",
+    "d14bddd54cb71b13554b1acae37ffecc936bd448db604d2796a94c812482133e",
+    "343a9d0e1b1a206519a0dd8255be656014fc1e89efad6871a111bc0837ec13b8",
+    "86c94bd08cf41a22220a203cc289d22a301557d04e3e88b76b1299785a1dee92",
+    "c1ccb2334dc86c98501bc1280130904e38904e40f7b7df81923048f787b2b097",
+    "30520410e0a71258047002"
+);
+
+/// Message of roughly `size` bytes made of `count` length-delimited fields in reverse field ID order
+fn synthetic_message(size: usize, count: usize) -> Vec<u8> {
+    let payload_size = size / count;
+    let mut msg = Vec::with_capacity(size + count * 8);
+    for i in 0..count {
+        let field_id = (count - i) as u64;
+        push_varint(&mut msg, field_id << 3 | 2);
+        push_varint(&mut msg, payload_size as u64);
+        msg.resize(msg.len() + payload_size, i as u8);
+    }
+    msg
+}
+
+fn push_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn bench_sort(c: &mut Criterion) {
+    let messages = [
+        ("canonical", hex::decode(CANONICAL_FIELD_ORDER).unwrap()),
+        ("non_canonical", hex::decode(NON_CANONICAL_FIELD_ORDER).unwrap()),
+        ("1KB_few_fields", synthetic_message(1 << 10, 8)),
+        ("100KB_many_small_fields", synthetic_message(100 << 10, 10_000)),
+        ("1MB_few_large_payloads", synthetic_message(1 << 20, 4)),
+    ];
+    let mut group = c.benchmark_group("sort");
+    for (name, msg) in &messages {
+        group.throughput(Throughput::Bytes(msg.len() as u64));
+        group.bench_with_input(BenchmarkId::new("sort_protobuf_message", name), msg, |b, msg| {
+            b.iter(|| sort_protobuf_message(black_box(msg)).map(|sorted| sorted.len()))
+        });
+        group.bench_with_input(BenchmarkId::new("full_sort", name), msg, |b, msg| {
+            b.iter(|| _bench_sort_message_internal(black_box(msg)))
+        });
+    }
+    group.finish();
+}
+
+/// Stable vs unstable sort of 1000 occurrences of one repeated field followed by a lower field ID
+fn bench_sort_stability(c: &mut Criterion) {
+    let mut msg = Vec::new();
+    for i in 0..1000u64 {
+        push_varint(&mut msg, 2 << 3);
+        push_varint(&mut msg, i);
+    }
+    msg.extend_from_slice(&[0x08, 0x01]);
+    let mut group = c.benchmark_group("sort_stability");
+    group.throughput(Throughput::Bytes(msg.len() as u64));
+    for stable in [true, false] {
+        let options = SortOptions::default().stable(stable);
+        let name = if stable { "stable" } else { "unstable" };
+        group.bench_with_input(BenchmarkId::new(name, "1000_repeated"), &msg, |b, msg| {
+            b.iter(|| sort_protobuf_message_with_options(black_box(msg), &options).map(|sorted| sorted.len()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sort, bench_sort_stability);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+//! Varint decoding benchmark, compare the results of
+//! `cargo bench --bench varint` and `cargo bench --bench varint --features simd`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use protofixer::{is_protobuf_message_sorted, sort_protobuf_message};
+
+/// Message with `count` small varint fields in descending field ID order
+fn small_varints_message(count: usize) -> Vec<u8> {
+    let mut msg = Vec::with_capacity(count * 2);
+    for i in 0..count {
+        let field_id = 15 - (i * 15 / count) as u8;
+        msg.push(field_id << 3);
+        msg.push((i % 128) as u8);
+    }
+    msg
+}
+
+fn bench_small_varints(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_varints");
+    for count in [1_000, 10_000] {
+        let msg = small_varints_message(count);
+        group.throughput(Throughput::Bytes(msg.len() as u64));
+        group.bench_function(format!("is_sorted/{}", count), |b| {
+            b.iter(|| is_protobuf_message_sorted(black_box(&msg)))
+        });
+        group.bench_function(format!("sort/{}", count), |b| {
+            b.iter(|| sort_protobuf_message(black_box(&msg)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_small_varints);
+criterion_main!(benches);
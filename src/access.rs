@@ -0,0 +1,136 @@
+use crate::{parse_message, ChunkVec, ParseError};
+use std::collections::HashMap;
+
+/// Get the payload of a nested field by the path of field IDs, e.g. for `path = [2, 5, 1]`
+/// returns the payload of field 1 inside field 5 inside field 2 of the given message.
+///
+/// All the fields on the path but the last must be length-delimited (embedded messages).
+/// If a field occurs several times, the last occurrence is used, like protobuf decoders do
+/// for singular fields. Returns `None` if any field on the path is missing.
+pub fn get_nested_field_bytes<'a>(msg: &'a [u8], path: &[u32]) -> Result<Option<&'a [u8]>, ParseError> {
+    let mut bytes = msg;
+    for (i, &field_id) in path.iter().enumerate() {
+        let chunks = parse_message(bytes)?;
+        let chunk = match chunks.iter().rev().find(|ck| ck.id == field_id) {
+            Some(chunk) => chunk,
+            None => return Ok(None),
+        };
+        if i + 1 < path.len() && chunk.wire_type != 2 {
+            // Not an embedded message
+            return Err(ParseError);
+        }
+        bytes = chunk.payload(bytes);
+    }
+    Ok(Some(bytes))
+}
+
+/// Collect all fields of the given message as `(field_id, payload)` pairs, in the message order.
+///
+/// Payload excludes the tag and, for length-delimited fields, the length prefix,
+/// see [`FieldDecoder`](crate::FieldDecoder) for decoding it.
+pub fn collect_field_payloads(msg: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(chunks.iter().map(|ck| (ck.id, ck.payload(msg).to_vec())).collect())
+}
+
+/// Field lookup table for repeated queries on the same message without re-parsing it.
+pub struct MessageIndex<'a> {
+    msg: &'a [u8],
+    chunks: ChunkVec,
+    /// Positions of chunks in `chunks` by field ID
+    index: HashMap<u32, Vec<usize>>,
+}
+
+/// Parse the given message and build its field lookup table.
+pub fn build_index(msg: &[u8]) -> Result<MessageIndex<'_>, ParseError> {
+    let chunks = parse_message(msg)?;
+    let mut index: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, ck) in chunks.iter().enumerate() {
+        index.entry(ck.id).or_default().push(i);
+    }
+    Ok(MessageIndex { msg, chunks, index })
+}
+
+impl<'a> MessageIndex<'a> {
+    /// Payload of the last occurrence of the field, like protobuf decoders use for singular fields.
+    pub fn get(&self, field_id: u32) -> Option<&'a [u8]> {
+        let positions = self.index.get(&field_id)?;
+        positions.last().map(|&i| self.chunks[i].payload(self.msg))
+    }
+
+    /// Payloads of all occurrences of the field, in the message order.
+    pub fn get_all(&self, field_id: u32) -> impl Iterator<Item = &'a [u8]> + '_ {
+        let positions = self.index.get(&field_id).map_or(&[][..], |p| p.as_slice());
+        positions.iter().map(move |&i| self.chunks[i].payload(self.msg))
+    }
+
+    pub fn has(&self, field_id: u32) -> bool {
+        self.index.contains_key(&field_id)
+    }
+
+    /// Number of occurrences of the field.
+    pub fn count(&self, field_id: u32) -> usize {
+        self.index.get(&field_id).map_or(0, |p| p.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_index, collect_field_payloads, get_nested_field_bytes};
+
+    #[test]
+    fn test_get_nested_field_bytes() {
+        // Field 2 { field 5 { field 1: 150 } }, field 3: 1
+        let msg = [0x12, 0x05, 0x2a, 0x03, 0x08, 0x96, 0x01, 0x18, 0x01];
+        assert_eq!(
+            get_nested_field_bytes(&msg, &[2, 5, 1]).unwrap(),
+            Some([0x96, 0x01].as_slice())
+        );
+        assert_eq!(
+            get_nested_field_bytes(&msg, &[2, 5]).unwrap(),
+            Some([0x08, 0x96, 0x01].as_slice())
+        );
+        assert_eq!(get_nested_field_bytes(&msg, &[3]).unwrap(), Some([0x01].as_slice()));
+        assert_eq!(get_nested_field_bytes(&msg, &[]).unwrap(), Some(msg.as_slice()));
+        assert_eq!(get_nested_field_bytes(&msg, &[2, 4, 1]).unwrap(), None);
+        assert_eq!(get_nested_field_bytes(&msg, &[7]).unwrap(), None);
+        assert!(get_nested_field_bytes(&msg, &[3, 1]).is_err());
+        assert!(get_nested_field_bytes(&[0x12, 0x01, 0x0F], &[2, 1]).is_err());
+    }
+
+    #[test]
+    fn test_get_nested_field_bytes_last_occurrence() {
+        let msg = [0x08, 0x01, 0x08, 0x02];
+        assert_eq!(get_nested_field_bytes(&msg, &[1]).unwrap(), Some([0x02].as_slice()));
+    }
+
+    #[test]
+    fn test_collect_field_payloads() {
+        let msg = [0x18, 0x96, 0x01, 0x0a, 0x02, 0xab, 0xcd, 0x15, 1, 2, 3, 4, 0x18, 0x00];
+        let expected = vec![
+            (3, vec![0x96, 0x01]),
+            (1, vec![0xab, 0xcd]),
+            (2, vec![1, 2, 3, 4]),
+            (3, vec![0x00]),
+        ];
+        assert_eq!(collect_field_payloads(&msg).unwrap(), expected);
+        assert!(collect_field_payloads(&[]).unwrap().is_empty());
+        assert!(collect_field_payloads(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_message_index() {
+        let msg = [0x18, 0x96, 0x01, 0x0a, 0x02, 0xab, 0xcd, 0x18, 0x00];
+        let index = build_index(&msg).unwrap();
+        assert_eq!(index.get(1), Some([0xab, 0xcd].as_slice()));
+        assert_eq!(index.get(3), Some([0x00].as_slice()));
+        assert_eq!(index.get(2), None);
+        assert_eq!(index.get_all(3).collect::<Vec<_>>(), [[0x96, 0x01].as_slice(), &[0x00]]);
+        assert_eq!(index.get_all(2).count(), 0);
+        assert!(index.has(1));
+        assert!(!index.has(2));
+        assert_eq!(index.count(3), 2);
+        assert_eq!(index.count(2), 0);
+        assert!(build_index(&[0x0F]).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{is_sorted, parse_field, sort_chunks, ParseError};
+use std::alloc::Allocator;
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// allocating the transient fields list and the output buffer with the given allocator.
+///
+/// Returns `None` if the message is already sorted.
+pub fn sort_protobuf_message_in<A: Allocator + Clone>(msg: &[u8], alloc: A) -> Result<Option<Vec<u8, A>>, ParseError> {
+    let mut chunks = Vec::new_in(alloc.clone());
+    let mut offset = 0;
+    while offset < msg.len() {
+        let chunk = parse_field(msg, offset)?;
+        offset += chunk.length;
+        chunks.push(chunk);
+    }
+    if is_sorted(&chunks) {
+        return Ok(None);
+    }
+    sort_chunks(&mut chunks);
+    let mut sorted = Vec::with_capacity_in(msg.len(), alloc);
+    for ck in &chunks {
+        sorted.extend_from_slice(ck.bytes(msg));
+    }
+    Ok(Some(sorted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_protobuf_message_in;
+    use std::alloc::Global;
+
+    #[test]
+    fn test_sort_message_in() {
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02];
+        let sorted = sort_protobuf_message_in(&msg, Global).unwrap().unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x10, 0x02, 0x18, 0x03]);
+        assert!(sort_protobuf_message_in(&sorted, Global).unwrap().is_none());
+        assert!(sort_protobuf_message_in(&[0x0F], Global).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{sort_protobuf_message_chunked, ParseError};
+use std::io;
+use thiserror::Error;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+#[derive(Debug, Error)]
+pub enum AsyncSortError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and write them to `writer` field by field, without copying the message bytes.
+///
+/// Sorting itself is synchronous, since it's CPU-bound. Nothing is written if the message
+/// is malformed. The writer is not flushed.
+pub async fn sort_protobuf_message_async_write<W: AsyncWrite + Unpin>(
+    msg: &[u8],
+    writer: &mut W,
+) -> Result<(), AsyncSortError> {
+    let sorted = sort_protobuf_message_chunked(msg)?;
+    for chunk in sorted.iter_chunks() {
+        writer.write_all(chunk).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_async_write, AsyncSortError};
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_sort_message_async_write() {
+        let mut output = Vec::new();
+        block_on(sort_protobuf_message_async_write(
+            &[0x18, 0x03, 0x08, 0x01],
+            &mut output,
+        ))
+        .unwrap();
+        assert_eq!(output, [0x08, 0x01, 0x18, 0x03]);
+        let mut output = Vec::new();
+        assert!(matches!(
+            block_on(sort_protobuf_message_async_write(&[0x08, 0x01, 0x0F], &mut output)),
+            Err(AsyncSortError::Parse(_))
+        ));
+        assert!(output.is_empty());
+        let mut buf = [0u8; 3];
+        let mut writer = std::io::Cursor::new(buf.as_mut_slice());
+        assert!(matches!(
+            block_on(sort_protobuf_message_async_write(
+                &[0x18, 0x03, 0x08, 0x01],
+                &mut writer
+            )),
+            Err(AsyncSortError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{into_canonical_bytes, sort_protobuf_message, ParseError};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum DecodeOrParseError {
+    #[error("invalid base64: {0}")]
+    Decode(#[from] base64::DecodeError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and encode the result as standard base64 (with padding).
+pub fn sort_protobuf_message_to_base64(msg: &[u8]) -> Result<String, ParseError> {
+    Ok(STANDARD.encode(sort_protobuf_message(msg)?))
+}
+
+/// Decode the given standard base64 (with padding) protobuf message and sort its fields
+/// in the "canonical" order (by field ID).
+pub fn sort_protobuf_message_from_base64(b64: &str) -> Result<Vec<u8>, DecodeOrParseError> {
+    Ok(into_canonical_bytes(STANDARD.decode(b64)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_from_base64, sort_protobuf_message_to_base64, DecodeOrParseError};
+
+    #[test]
+    fn test_sort_message_base64() {
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        assert_eq!(sort_protobuf_message_to_base64(&msg).unwrap(), "CAEQAg==");
+        assert!(sort_protobuf_message_to_base64(&[0x0F]).is_err());
+        assert_eq!(
+            sort_protobuf_message_from_base64("EAIIAQ==").unwrap(),
+            [0x08, 0x01, 0x10, 0x02]
+        );
+        assert!(matches!(
+            sort_protobuf_message_from_base64("EAIIAQ"),
+            Err(DecodeOrParseError::Decode(_))
+        ));
+        assert!(matches!(
+            sort_protobuf_message_from_base64("Dw=="),
+            Err(DecodeOrParseError::Parse(_))
+        ));
+    }
+}
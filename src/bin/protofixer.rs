@@ -0,0 +1,140 @@
+//! Sort fields of a protobuf message read from a file or stdin, build with `--features cli`.
+
+use clap::Parser;
+use protofixer::{
+    is_protobuf_message_sorted, parse_fields, sort_protobuf_file_contents_in_place, sort_protobuf_message,
+};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::{fmt, fs};
+
+/// Sort fields of a serialized protobuf message in the canonical order (by field ID).
+#[derive(Debug, Parser)]
+#[command(name = "protofixer")]
+struct Args {
+    /// Input file, stdin if omitted
+    input: Option<PathBuf>,
+    /// Output file, stdout if omitted
+    #[arg(conflicts_with_all = ["check", "in_place"])]
+    output: Option<PathBuf>,
+    /// Only check the fields order: exit with 0 if sorted, 1 if not
+    #[arg(long, conflicts_with = "in_place")]
+    check: bool,
+    /// Sort the input file in place
+    #[arg(long, requires = "input")]
+    in_place: bool,
+    /// Print the number of fields and their sizes to stderr
+    #[arg(long)]
+    stats: bool,
+}
+
+/// Exit code of failures, to tell them from unsorted input in `--check` mode
+const EXIT_FAILURE: u8 = 2;
+
+fn main() -> ExitCode {
+    match run(&Args::parse()) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("protofixer: {}", err);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let msg = match &args.input {
+        Some(path) => fs::read(path)?,
+        None => {
+            let mut msg = Vec::new();
+            io::stdin().read_to_end(&mut msg)?;
+            msg
+        }
+    };
+    if args.stats {
+        eprint!("{}", MessageStats::new(&msg)?);
+    }
+    if args.check {
+        let sorted = is_protobuf_message_sorted(&msg)?;
+        return Ok(if sorted { ExitCode::SUCCESS } else { ExitCode::FAILURE });
+    }
+    if args.in_place {
+        if let Some(path) = &args.input {
+            // Sort the bytes `--stats` reported on, without reading the file again
+            sort_protobuf_file_contents_in_place(path, &msg)?;
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    let sorted = sort_protobuf_message(&msg)?;
+    match &args.output {
+        Some(path) => fs::write(path, &sorted)?,
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&sorted)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Field count and sizes of a message, for `--stats`
+struct MessageStats {
+    total_bytes: usize,
+    field_count: usize,
+    /// Number of occurrences and their total size in bytes, including tags, by field ID
+    fields: BTreeMap<u32, (usize, usize)>,
+}
+
+impl MessageStats {
+    fn new(msg: &[u8]) -> Result<MessageStats, protofixer::ParseError> {
+        let parsed = parse_fields(msg)?;
+        let mut fields: BTreeMap<u32, (usize, usize)> = BTreeMap::new();
+        for field in &parsed {
+            let (count, bytes) = fields.entry(field.id()).or_default();
+            *count += 1;
+            *bytes += field.bytes().len();
+        }
+        Ok(MessageStats {
+            total_bytes: msg.len(),
+            field_count: parsed.len(),
+            fields,
+        })
+    }
+}
+
+impl fmt::Display for MessageStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} fields, {} bytes", self.field_count, self.total_bytes)?;
+        for (id, (count, bytes)) in &self.fields {
+            writeln!(f, "field {}: {} occurrences, {} bytes", id, count, bytes)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, MessageStats};
+    use clap::{CommandFactory, Parser};
+
+    #[test]
+    fn test_args() {
+        Args::command().debug_assert();
+        let args = Args::try_parse_from(["protofixer", "--check", "in.pb"]).unwrap();
+        assert!(args.check && !args.in_place);
+        assert!(Args::try_parse_from(["protofixer", "--in-place"]).is_err());
+        assert!(Args::try_parse_from(["protofixer", "--check", "in.pb", "out.pb"]).is_err());
+    }
+
+    #[test]
+    fn test_message_stats() {
+        // Fields 3 ("ab"), 1, 3 (empty)
+        let stats = MessageStats::new(&[0x1a, 0x02, b'a', b'b', 0x08, 0x01, 0x1a, 0x00]).unwrap();
+        assert_eq!(
+            stats.to_string(),
+            "3 fields, 8 bytes\nfield 1: 1 occurrences, 2 bytes\nfield 3: 2 occurrences, 6 bytes\n"
+        );
+        assert!(MessageStats::new(&[0x0F]).is_err());
+    }
+}
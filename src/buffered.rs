@@ -0,0 +1,64 @@
+use crate::{sort_protobuf_message_inplace, ParseError};
+use std::io::{self, BufRead};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SortReadError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("protobuf message exceeds the limit of {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Read the protobuf message from the given reader until EOF and sort its fields in the
+/// "canonical" order (by field ID).
+///
+/// The message is copied straight from the reader's buffer and sorted in place, and reading stops
+/// as soon as it exceeds `max_bytes`, without consuming more than that.
+pub fn sort_protobuf_message_buffered<R: BufRead>(mut reader: R, max_bytes: usize) -> Result<Vec<u8>, SortReadError> {
+    let mut msg = Vec::new();
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(buf) => buf,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if buf.len() > max_bytes - msg.len() {
+            return Err(SortReadError::TooLarge(max_bytes));
+        }
+        msg.extend_from_slice(buf);
+        let length = buf.len();
+        reader.consume(length);
+    }
+    sort_protobuf_message_inplace(&mut msg)?;
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_buffered, SortReadError};
+    use std::io::BufReader;
+
+    #[test]
+    fn test_sort_protobuf_message_buffered() {
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x12, 0x01, b'a'];
+        // Buffer smaller than the message
+        let reader = BufReader::with_capacity(2, msg.as_slice());
+        assert_eq!(
+            sort_protobuf_message_buffered(reader, 7).unwrap(),
+            [0x08, 0x01, 0x12, 0x01, b'a', 0x18, 0x03]
+        );
+        assert!(sort_protobuf_message_buffered([].as_slice(), 0).unwrap().is_empty());
+        assert!(matches!(
+            sort_protobuf_message_buffered(msg.as_slice(), 6),
+            Err(SortReadError::TooLarge(6))
+        ));
+        assert!(matches!(
+            sort_protobuf_message_buffered([0x0F].as_slice(), 10),
+            Err(SortReadError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::encode::encode_tag;
+use crate::{write_varint, EncodeError, WireType};
+use std::ops::Deref;
+
+/// Builds a protobuf message with fields in the "canonical" order (by field ID)
+/// from field payloads added in any order.
+#[derive(Debug, Clone, Default)]
+pub struct MessageBuilder {
+    fields: Vec<(u32, WireType, Vec<u8>)>,
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field with the given raw payload, i.e. without the tag and the length prefix.
+    pub fn push_field(&mut self, field_id: u32, wire_type: WireType, payload: &[u8]) -> &mut Self {
+        self.fields.push((field_id, wire_type, payload.to_vec()));
+        self
+    }
+
+    /// Add a varint field with the given value.
+    pub fn push_varint(&mut self, field_id: u32, value: u64) -> &mut Self {
+        let mut payload = Vec::new();
+        write_varint(u128::from(value), &mut payload);
+        self.fields.push((field_id, WireType::Varint, payload));
+        self
+    }
+
+    /// Add an enum field with the given value. Like any `int32`, negative values are
+    /// sign-extended to 64 bits, so they always take 10 bytes.
+    pub fn push_enum<E: Into<i32>>(&mut self, field_id: u32, value: E) -> &mut Self {
+        self.push_varint(field_id, i64::from(value.into()) as u64)
+    }
+
+    /// Add a length-delimited field with the given bytes.
+    pub fn push_bytes(&mut self, field_id: u32, bytes: &[u8]) -> &mut Self {
+        self.push_field(field_id, WireType::LengthDelimited, bytes)
+    }
+
+    /// Encode the added fields, sorted by field ID. Repeated fields keep the order they were added in.
+    pub fn build(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut fields: Vec<_> = self.fields.iter().collect();
+        fields.sort_by_key(|(field_id, _, _)| *field_id);
+        let mut msg = Vec::new();
+        for (field_id, wire_type, payload) in fields {
+            if !wire_type.is_valid_payload(payload) {
+                return Err(EncodeError::InvalidPayload(*field_id));
+            }
+            encode_tag(*field_id, *wire_type as u8, &mut msg)?;
+            if *wire_type == WireType::LengthDelimited {
+                write_varint(payload.len() as u128, &mut msg);
+            }
+            msg.extend_from_slice(payload);
+        }
+        Ok(msg)
+    }
+}
+
+impl<'a> Extend<(u32, WireType, &'a [u8])> for MessageBuilder {
+    fn extend<I: IntoIterator<Item = (u32, WireType, &'a [u8])>>(&mut self, iter: I) {
+        for (field_id, wire_type, payload) in iter {
+            self.push_field(field_id, wire_type, payload);
+        }
+    }
+}
+
+/// Computes the encoded size of a protobuf message without encoding it,
+/// e.g. to allocate a buffer of the exact size for [`MessageBuilder`] output.
+#[derive(Debug, Clone, Default)]
+pub struct MessageSizeEstimator {
+    size: usize,
+}
+
+impl MessageSizeEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for a varint field with the given value.
+    pub fn add_varint_field(&mut self, field_id: u32, value: u64) -> &mut Self {
+        self.size += tag_length(field_id) + varint_length(value);
+        self
+    }
+
+    /// Account for a length-delimited field with a payload of `len` bytes.
+    pub fn add_bytes_field(&mut self, field_id: u32, len: usize) -> &mut Self {
+        self.size += tag_length(field_id) + varint_length(len as u64) + len;
+        self
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.size
+    }
+}
+
+pub(crate) fn tag_length(field_id: u32) -> usize {
+    // Wire type bits don't affect the length
+    varint_length(u64::from(field_id) << 3)
+}
+
+fn varint_length(value: u64) -> usize {
+    // Each byte carries 7 bits, zero takes one byte
+    (u64::BITS - (value | 1).leading_zeros()).div_ceil(7) as usize
+}
+
+/// Protobuf message bytes with fields in the "canonical" order, collected from
+/// `(field_id, wire_type, payload)` items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortedMessageBytes(Vec<u8>);
+
+impl SortedMessageBytes {
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for SortedMessageBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'a> FromIterator<(u32, WireType, &'a [u8])> for SortedMessageBytes {
+    /// # Panics
+    ///
+    /// Panics if a field ID or payload is invalid, use [`MessageBuilder`] to handle these errors.
+    fn from_iter<I: IntoIterator<Item = (u32, WireType, &'a [u8])>>(iter: I) -> Self {
+        let mut builder = MessageBuilder::new();
+        builder.extend(iter);
+        SortedMessageBytes(builder.build().expect("invalid protobuf field"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{varint_length, MessageBuilder, MessageSizeEstimator, SortedMessageBytes};
+    use crate::{EncodeError, WireType};
+
+    #[test]
+    fn test_message_builder() {
+        let mut builder = MessageBuilder::new();
+        builder
+            .push_bytes(3, b"ab")
+            .push_varint(1, 150)
+            .push_field(2, WireType::Fixed32, &[1, 2, 3, 4])
+            .push_varint(1, 2);
+        assert_eq!(
+            builder.build().unwrap(),
+            [0x08, 0x96, 0x01, 0x08, 0x02, 0x15, 1, 2, 3, 4, 0x1a, 0x02, b'a', b'b']
+        );
+        assert!(MessageBuilder::new().build().unwrap().is_empty());
+        let mut builder = MessageBuilder::new();
+        builder.push_varint(0, 1);
+        assert!(matches!(builder.build(), Err(EncodeError::InvalidFieldId(0))));
+        let mut builder = MessageBuilder::new();
+        builder.push_field(2, WireType::Fixed64, &[1, 2, 3, 4]);
+        assert!(matches!(builder.build(), Err(EncodeError::InvalidPayload(2))));
+    }
+
+    #[test]
+    fn test_push_enum() {
+        let mut builder = MessageBuilder::new();
+        builder.push_enum(2, -1).push_enum(1, 3u8);
+        assert_eq!(
+            builder.build().unwrap(),
+            [0x08, 0x03, 0x10, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_message_size_estimator() {
+        let mut estimator = MessageSizeEstimator::new();
+        estimator
+            .add_varint_field(1, 150)
+            .add_bytes_field(16, 300)
+            .add_varint_field(2, 0);
+        let mut builder = MessageBuilder::new();
+        builder.push_varint(1, 150).push_bytes(16, &[0; 300]).push_varint(2, 0);
+        assert_eq!(estimator.total_size(), builder.build().unwrap().len());
+        assert_eq!(MessageSizeEstimator::new().total_size(), 0);
+    }
+
+    #[test]
+    fn test_varint_length() {
+        assert_eq!(varint_length(0), 1);
+        assert_eq!(varint_length(127), 1);
+        assert_eq!(varint_length(128), 2);
+        assert_eq!(varint_length(u64::MAX), 10);
+    }
+
+    #[test]
+    fn test_extend_and_collect() {
+        let fields = [
+            (2, WireType::LengthDelimited, b"x".as_slice()),
+            (1, WireType::Varint, &[0x05]),
+        ];
+        let mut builder = MessageBuilder::new();
+        builder.extend(fields);
+        assert_eq!(builder.build().unwrap(), [0x08, 0x05, 0x12, 0x01, b'x']);
+        let collected: SortedMessageBytes = fields.into_iter().collect();
+        assert_eq!(*collected, [0x08, 0x05, 0x12, 0x01, b'x']);
+        assert_eq!(collected.into_inner(), [0x08, 0x05, 0x12, 0x01, b'x']);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{sort_protobuf_message, ParseError};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+
+/// Cache of recently sorted protobuf messages, keyed by BLAKE3 hash of the original message.
+///
+/// Least recently used entries are evicted once the capacity is reached.
+#[derive(Debug)]
+pub struct SortingCache {
+    inner: LruCache<[u8; 32], Vec<u8>>,
+}
+
+impl SortingCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        SortingCache {
+            inner: LruCache::new(capacity),
+        }
+    }
+
+    /// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+    /// or return the cached result if the same message was sorted recently.
+    pub fn sort(&mut self, msg: &[u8]) -> Result<&[u8], ParseError> {
+        let key = *blake3::hash(msg).as_bytes();
+        let sorted = self
+            .inner
+            .try_get_or_insert(key, || Ok(sort_protobuf_message(msg)?.into_owned()))?;
+        Ok(sorted)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SortingCache;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_sorting_cache() {
+        let mut cache = SortingCache::new(NonZeroUsize::new(2).unwrap());
+        assert!(cache.is_empty());
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        assert_eq!(cache.sort(&msg).unwrap(), [0x08, 0x01, 0x10, 0x02]);
+        assert_eq!(cache.sort(&msg).unwrap(), [0x08, 0x01, 0x10, 0x02]);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.sort(&[0x0F]).is_err());
+        assert_eq!(cache.len(), 1);
+        cache.sort(&[0x08, 0x01]).unwrap();
+        cache.sort(&[0x10, 0x02]).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.sort(&msg).unwrap(), [0x08, 0x01, 0x10, 0x02]);
+    }
+}
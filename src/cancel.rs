@@ -0,0 +1,74 @@
+use crate::{sort_protobuf_message, ParseError};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Shared flag to request cancellation of a sort from another task or thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request cancellation; all the clones of this token are cancelled as well.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum CancellableSortError {
+    #[error("sort was cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// unless the token is cancelled. The token is checked before and after sorting only.
+pub fn sort_protobuf_message_cancellable<'a>(
+    msg: &'a [u8],
+    cancel_token: &CancellationToken,
+) -> Result<Cow<'a, [u8]>, CancellableSortError> {
+    if cancel_token.is_cancelled() {
+        return Err(CancellableSortError::Cancelled);
+    }
+    let sorted = sort_protobuf_message(msg)?;
+    if cancel_token.is_cancelled() {
+        return Err(CancellableSortError::Cancelled);
+    }
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_cancellable, CancellableSortError, CancellationToken};
+
+    #[test]
+    fn test_sort_message_cancellable() {
+        let token = CancellationToken::new();
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        assert_eq!(
+            sort_protobuf_message_cancellable(&msg, &token).unwrap().as_ref(),
+            [0x08, 0x01, 0x10, 0x02]
+        );
+        assert!(matches!(
+            sort_protobuf_message_cancellable(&[0x0F], &token),
+            Err(CancellableSortError::Parse(_))
+        ));
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(
+            sort_protobuf_message_cancellable(&msg, &token),
+            Err(CancellableSortError::Cancelled)
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{sorted_chunks, ParseError};
+
+/// Error of [`sort_and_convert_to_cbor`]
+pub type CborConvertError = ParseError;
+
+const CBOR_BYTES: u8 = 2;
+const CBOR_ARRAY: u8 = 4;
+const CBOR_MAP: u8 = 5;
+
+/// Convert the given protobuf message to a deterministically encoded CBOR map from field IDs
+/// (unsigned integers, ascending) to raw field payloads (byte strings).
+///
+/// Payloads don't include the tag and the length prefix. A repeated field maps to an array
+/// of its payloads, in the message order.
+pub fn sort_and_convert_to_cbor(msg: &[u8]) -> Result<Vec<u8>, CborConvertError> {
+    let chunks = sorted_chunks(msg)?;
+    let mut fields: Vec<(u32, Vec<&[u8]>)> = Vec::new();
+    for ck in &chunks {
+        match fields.last_mut() {
+            Some((id, payloads)) if *id == ck.id => payloads.push(ck.payload(msg)),
+            _ => fields.push((ck.id, vec![ck.payload(msg)])),
+        }
+    }
+    let mut cbor = Vec::with_capacity(msg.len() + 2 * fields.len() + 9);
+    write_cbor_head(CBOR_MAP, fields.len() as u64, &mut cbor);
+    for (id, payloads) in &fields {
+        write_cbor_head(0, u64::from(*id), &mut cbor);
+        if payloads.len() > 1 {
+            write_cbor_head(CBOR_ARRAY, payloads.len() as u64, &mut cbor);
+        }
+        for payload in payloads {
+            write_cbor_head(CBOR_BYTES, payload.len() as u64, &mut cbor);
+            cbor.extend_from_slice(payload);
+        }
+    }
+    Ok(cbor)
+}
+
+/// Write CBOR data item head with the shortest encoding of the argument
+fn write_cbor_head(major_type: u8, value: u64, out: &mut Vec<u8>) {
+    let major_type = major_type << 5;
+    match value {
+        0..=23 => out.push(major_type | value as u8),
+        24..=0xff => out.extend_from_slice(&[major_type | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major_type | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major_type | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major_type | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_and_convert_to_cbor, write_cbor_head};
+
+    #[test]
+    fn test_sort_and_convert_to_cbor() {
+        // Fields 300 ("hi"), 1 (150), 2 (1), 2 (2)
+        let msg = [0xe2, 0x12, 0x02, b'h', b'i', 0x08, 0x96, 0x01, 0x10, 0x01, 0x10, 0x02];
+        assert_eq!(
+            sort_and_convert_to_cbor(&msg).unwrap(),
+            [0xa3, 0x01, 0x42, 0x96, 0x01, 0x02, 0x82, 0x41, 0x01, 0x41, 0x02, 0x19, 0x01, 0x2c, 0x42, b'h', b'i']
+        );
+        assert_eq!(sort_and_convert_to_cbor(&[]).unwrap(), [0xa0]);
+        assert!(sort_and_convert_to_cbor(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_write_cbor_head() {
+        let head = |value| {
+            let mut out = Vec::new();
+            write_cbor_head(0, value, &mut out);
+            out
+        };
+        assert_eq!(head(23), [0x17]);
+        assert_eq!(head(24), [0x18, 0x18]);
+        assert_eq!(head(1000), [0x19, 0x03, 0xe8]);
+        assert_eq!(head(1_000_000), [0x1a, 0x00, 0x0f, 0x42, 0x40]);
+        assert_eq!(head(u64::MAX), [0x1b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    into_canonical_bytes, is_sorted, parse_message, sort_chunks, sort_protobuf_message, sorted_chunks, ChunkVec,
+    ParseError,
+};
+use std::borrow::Cow;
+use std::io::{self, IoSlice};
+
+/// Sorted protobuf message that is not materialized as a single contiguous buffer.
+///
+/// Holds only the sorted fields layout and refers to the bytes of the original message.
+/// Implements [`io::Read`] yielding the sorted message bytes.
+pub struct ChunkedSortedMessage<'a> {
+    msg: &'a [u8],
+    chunks: ChunkVec,
+    /// Read cursor: index of the current chunk and offset in it
+    read_pos: (usize, usize),
+}
+
+impl<'a> ChunkedSortedMessage<'a> {
+    /// Iterate over the fields bytes of the sorted message, in the sorted order.
+    pub fn iter_chunks(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        self.chunks.iter().map(|ck| ck.bytes(self.msg))
+    }
+}
+
+impl io::Read for ChunkedSortedMessage<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let (index, offset) = self.read_pos;
+            let Some(ck) = self.chunks.get(index) else {
+                break;
+            };
+            let bytes = &ck.bytes(self.msg)[offset..];
+            let n = bytes.len().min(buf.len() - written);
+            buf[written..written + n].copy_from_slice(&bytes[..n]);
+            written += n;
+            self.read_pos = if n == bytes.len() {
+                (index + 1, 0)
+            } else {
+                (index, offset + n)
+            };
+        }
+        Ok(written)
+    }
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// without copying the message bytes.
+pub fn sort_protobuf_message_chunked(msg: &[u8]) -> Result<ChunkedSortedMessage<'_>, ParseError> {
+    let chunks = sorted_chunks(msg)?;
+    Ok(ChunkedSortedMessage {
+        msg,
+        chunks,
+        read_pos: (0, 0),
+    })
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and return them as `(field_id, raw_field_bytes_including_tag)` pairs,
+/// without reassembling them into a message.
+pub fn sorted_field_chunks(msg: &[u8]) -> Result<Vec<(u32, &[u8])>, ParseError> {
+    let chunks = sorted_chunks(msg)?;
+    Ok(chunks.iter().map(|ck| (ck.id, ck.bytes(msg))).collect())
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and return the fields bytes as slices for [`io::Write::write_vectored`].
+///
+/// An already sorted message is returned as a single slice.
+pub fn sort_protobuf_message_to_iov(msg: &[u8]) -> Result<Vec<IoSlice<'_>>, ParseError> {
+    let mut chunks = parse_message(msg)?;
+    if is_sorted(&chunks) {
+        return Ok(vec![IoSlice::new(msg)]);
+    }
+    sort_chunks(&mut chunks);
+    Ok(chunks.iter().map(|ck| IoSlice::new(ck.bytes(msg))).collect())
+}
+
+/// Concatenate the given raw fields bytes (each including the tag) into a message and sort it,
+/// the reverse of [`sorted_field_chunks`].
+///
+/// A concatenation that is not a valid protobuf message is returned unsorted,
+/// use [`concat_and_sort_field_bytes`] to reject it instead.
+pub fn concat_field_bytes(fields: &[&[u8]]) -> Vec<u8> {
+    let msg = fields.concat();
+    match sort_protobuf_message(&msg) {
+        Ok(Cow::Owned(sorted)) => sorted,
+        _ => msg,
+    }
+}
+
+/// Same as [`concat_field_bytes`], but fails unless each slice is exactly one valid field.
+pub fn concat_and_sort_field_bytes(fields: &[&[u8]]) -> Result<Vec<u8>, ParseError> {
+    for field in fields {
+        if parse_message(field)?.len() != 1 {
+            return Err(ParseError);
+        }
+    }
+    into_canonical_bytes(fields.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        concat_and_sort_field_bytes, concat_field_bytes, sort_protobuf_message_chunked, sort_protobuf_message_to_iov,
+        sorted_field_chunks,
+    };
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_sort_message_chunked() {
+        let msg = [0x18, 0x03, 0x0a, 0x01, 0xff, 0x10, 0x02];
+        let sorted = sort_protobuf_message_chunked(&msg).unwrap();
+        let chunks: Vec<&[u8]> = sorted.iter_chunks().collect();
+        assert_eq!(chunks, [[0x0a, 0x01, 0xff].as_slice(), &[0x10, 0x02], &[0x18, 0x03]]);
+        assert_eq!(sort_protobuf_message_chunked(&[]).unwrap().iter_chunks().count(), 0);
+        assert!(sort_protobuf_message_chunked(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_read_chunked_message() {
+        let msg = [0x18, 0x03, 0x0a, 0x01, 0xff, 0x10, 0x02];
+        let mut sorted = sort_protobuf_message_chunked(&msg).unwrap();
+        let mut buf = [0; 4];
+        assert_eq!(sorted.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0x0a, 0x01, 0xff, 0x10]);
+        let mut rest = Vec::new();
+        sorted.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, [0x02, 0x18, 0x03]);
+        assert_eq!(sorted.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sorted_field_chunks() {
+        let msg = [0x18, 0x03, 0x0a, 0x01, 0xff, 0x18, 0x02];
+        let chunks = sorted_field_chunks(&msg).unwrap();
+        assert_eq!(
+            chunks,
+            [
+                (1, [0x0a, 0x01, 0xff].as_slice()),
+                (3, &[0x18, 0x03]),
+                (3, &[0x18, 0x02])
+            ]
+        );
+        assert!(sorted_field_chunks(&[]).unwrap().is_empty());
+        assert!(sorted_field_chunks(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_to_iov() {
+        let msg = [0x18, 0x03, 0x0a, 0x01, 0xff, 0x10, 0x02];
+        let iov = sort_protobuf_message_to_iov(&msg).unwrap();
+        let slices: Vec<&[u8]> = iov.iter().map(|slice| &**slice).collect();
+        assert_eq!(slices, [[0x0a, 0x01, 0xff].as_slice(), &[0x10, 0x02], &[0x18, 0x03]]);
+        let mut sorted = Vec::new();
+        assert_eq!(sorted.write_vectored(&iov).unwrap(), msg.len());
+        assert_eq!(sorted, [0x0a, 0x01, 0xff, 0x10, 0x02, 0x18, 0x03]);
+        let iov = sort_protobuf_message_to_iov(&sorted).unwrap();
+        assert_eq!(iov.len(), 1);
+        assert_eq!(&*iov[0], sorted.as_slice());
+        assert!(sort_protobuf_message_to_iov(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_concat_field_bytes() {
+        let msg = [0x18, 0x03, 0x0a, 0x01, 0xff, 0x18, 0x02];
+        let fields: Vec<&[u8]> = sorted_field_chunks(&msg)
+            .unwrap()
+            .into_iter()
+            .map(|(_, bytes)| bytes)
+            .collect();
+        let sorted = [0x0a, 0x01, 0xff, 0x18, 0x03, 0x18, 0x02];
+        assert_eq!(concat_field_bytes(&fields), sorted);
+        assert_eq!(concat_and_sort_field_bytes(&fields).unwrap(), sorted);
+        let fields: [&[u8]; 2] = [&[0x10, 0x02], &[0x08, 0x01]];
+        assert_eq!(concat_field_bytes(&fields), [0x08, 0x01, 0x10, 0x02]);
+        assert!(concat_field_bytes(&[]).is_empty());
+        // Malformed concatenation is kept as is
+        assert_eq!(concat_field_bytes(&[&[0x10, 0x02], &[0x0F]]), [0x10, 0x02, 0x0F]);
+    }
+
+    #[test]
+    fn test_concat_and_sort_field_bytes_errors() {
+        for fields in [
+            [[0x10, 0x02].as_slice(), &[0x0F]],
+            // Two fields in one slice
+            [&[0x10, 0x02, 0x08, 0x01], &[0x18, 0x03]],
+            // Field split across slices
+            [&[0x10], &[0x02]],
+            [&[], &[0x08, 0x01]],
+        ] {
+            assert!(concat_and_sort_field_bytes(&fields).is_err(), "{:02x?}", fields);
+        }
+        assert!(concat_and_sort_field_bytes(&[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{sort_protobuf_message, ParseError};
+use bytes::{Buf, BufMut, BytesMut};
+use std::io;
+use thiserror::Error;
+use tokio_util::codec::{Decoder, Encoder};
+
+#[derive(Debug, Error)]
+pub enum ProtoSortCodecError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("protobuf message of {0} bytes is larger than the maximum frame length")]
+    MessageTooLarge(usize),
+}
+
+/// Default maximum length of a framed message, the same as in [`tokio_util::codec::LengthDelimitedCodec`]
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Codec for [`tokio_util::codec::Framed`] streams of protobuf messages framed with 4-byte
+/// big-endian length prefixes, like [`sort_delimited_message_stream`](crate::sort_delimited_message_stream).
+///
+/// Both decoded and encoded messages are sorted in the "canonical" order (by field ID).
+/// Messages longer than the maximum frame length ([`DEFAULT_MAX_FRAME_LENGTH`] by default)
+/// fail with [`ProtoSortCodecError::MessageTooLarge`], before any buffer is reserved for them.
+#[derive(Debug, Clone, Copy)]
+pub struct ProtoSortCodec {
+    max_frame_length: usize,
+}
+
+impl ProtoSortCodec {
+    /// Codec accepting messages of up to `max_frame_length` bytes, not counting the length prefix.
+    pub fn new(max_frame_length: usize) -> Self {
+        Self { max_frame_length }
+    }
+}
+
+impl Default for ProtoSortCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FRAME_LENGTH)
+    }
+}
+
+impl Decoder for ProtoSortCodec {
+    type Item = Vec<u8>;
+    type Error = ProtoSortCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Vec<u8>>, ProtoSortCodecError> {
+        let Some(prefix) = src.get(..4) else {
+            return Ok(None);
+        };
+        let length = u32::from_be_bytes(prefix.try_into().expect("4 bytes")) as usize;
+        if length > self.max_frame_length {
+            // Untrusted length prefix, don't reserve the buffer for it
+            return Err(ProtoSortCodecError::MessageTooLarge(length));
+        }
+        if src.len() < 4 + length {
+            src.reserve(4 + length - src.len());
+            return Ok(None);
+        }
+        src.advance(4);
+        let msg = src.split_to(length);
+        Ok(Some(sort_protobuf_message(&msg)?.into_owned()))
+    }
+}
+
+impl Encoder<&[u8]> for ProtoSortCodec {
+    type Error = ProtoSortCodecError;
+
+    fn encode(&mut self, msg: &[u8], dst: &mut BytesMut) -> Result<(), ProtoSortCodecError> {
+        let length = u32::try_from(msg.len())
+            .ok()
+            .filter(|&length| length as usize <= self.max_frame_length)
+            .ok_or(ProtoSortCodecError::MessageTooLarge(msg.len()))?;
+        let sorted = sort_protobuf_message(msg)?;
+        dst.reserve(4 + sorted.len());
+        dst.put_u32(length);
+        dst.put_slice(&sorted);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProtoSortCodec, ProtoSortCodecError, DEFAULT_MAX_FRAME_LENGTH};
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn test_proto_sort_codec() {
+        let mut codec = ProtoSortCodec::default();
+        let mut buf = BytesMut::new();
+        codec.encode([0x10, 0x02, 0x08, 0x01].as_slice(), &mut buf).unwrap();
+        codec.encode([].as_slice(), &mut buf).unwrap();
+        assert_eq!(buf[..], [0, 0, 0, 4, 0x08, 0x01, 0x10, 0x02, 0, 0, 0, 0]);
+        assert!(matches!(
+            codec.encode([0x0F].as_slice(), &mut buf),
+            Err(ProtoSortCodecError::Parse(_))
+        ));
+
+        let mut src = BytesMut::from(&[0, 0, 0, 4, 0x10, 0x02][..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(&[0x08, 0x01, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap(), [0x08, 0x01, 0x10, 0x02]);
+        assert!(codec.decode(&mut src).unwrap().unwrap().is_empty());
+        // Half of the next length prefix is buffered
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(&[0, 0x01, 0x0F]);
+        assert!(matches!(codec.decode(&mut src), Err(ProtoSortCodecError::Parse(_))));
+    }
+
+    #[test]
+    fn test_proto_sort_codec_max_frame_length() {
+        let mut codec = ProtoSortCodec::default();
+        let mut src = BytesMut::from(&[0xff, 0xff, 0xff, 0xff][..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ProtoSortCodecError::MessageTooLarge(0xffff_ffff))
+        ));
+        assert!(src.capacity() < DEFAULT_MAX_FRAME_LENGTH);
+
+        let mut codec = ProtoSortCodec::new(2);
+        let mut buf = BytesMut::new();
+        codec.encode([0x08, 0x01].as_slice(), &mut buf).unwrap();
+        assert!(matches!(
+            codec.encode([0x10, 0x02, 0x08, 0x01].as_slice(), &mut buf),
+            Err(ProtoSortCodecError::MessageTooLarge(4))
+        ));
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), [0x08, 0x01]);
+        let mut src = BytesMut::from(&[0, 0, 0, 3][..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ProtoSortCodecError::MessageTooLarge(3))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{into_canonical_bytes, sort_protobuf_message, ParseError};
+use std::io;
+use thiserror::Error;
+
+/// Compression format of sorted messages, each one is enabled with its own feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionCodec {
+    /// Zstandard frame with the given compression level
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+    /// LZ4 block prefixed with its uncompressed size
+    #[cfg(feature = "lz4")]
+    Lz4,
+    /// Raw Snappy block
+    #[cfg(feature = "snappy")]
+    Snappy,
+}
+
+#[derive(Debug, Error)]
+pub enum CompressError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("compression failed: {0}")]
+    Compress(#[source] io::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum DecompressOrParseError {
+    #[error("decompression failed: {0}")]
+    Decompress(#[source] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and compress the result with the given codec.
+pub fn sort_and_compress_protobuf_message(msg: &[u8], codec: CompressionCodec) -> Result<Vec<u8>, CompressError> {
+    let sorted = sort_protobuf_message(msg)?;
+    match codec {
+        #[cfg(feature = "zstd")]
+        CompressionCodec::Zstd(level) => zstd::bulk::compress(&sorted, level).map_err(CompressError::Compress),
+        #[cfg(feature = "lz4")]
+        CompressionCodec::Lz4 => Ok(lz4_flex::compress_prepend_size(&sorted)),
+        #[cfg(feature = "snappy")]
+        CompressionCodec::Snappy => snap::raw::Encoder::new()
+            .compress_vec(&sorted)
+            .map_err(|err| CompressError::Compress(err.into())),
+    }
+}
+
+/// Decompress the given protobuf message with the given codec and sort its fields
+/// in the "canonical" order (by field ID).
+pub fn decompress_and_sort_protobuf_message(
+    compressed: &[u8],
+    codec: CompressionCodec,
+) -> Result<Vec<u8>, DecompressOrParseError> {
+    let msg = match codec {
+        #[cfg(feature = "zstd")]
+        CompressionCodec::Zstd(_) => zstd::decode_all(compressed),
+        #[cfg(feature = "lz4")]
+        CompressionCodec::Lz4 => lz4_flex::decompress_size_prepended(compressed)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        #[cfg(feature = "snappy")]
+        CompressionCodec::Snappy => snap::raw::Decoder::new()
+            .decompress_vec(compressed)
+            .map_err(io::Error::from),
+    }
+    .map_err(DecompressOrParseError::Decompress)?;
+    Ok(into_canonical_bytes(msg)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decompress_and_sort_protobuf_message, sort_and_compress_protobuf_message, CompressError, CompressionCodec,
+        DecompressOrParseError,
+    };
+
+    fn codecs() -> Vec<CompressionCodec> {
+        vec![
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd(3),
+            #[cfg(feature = "lz4")]
+            CompressionCodec::Lz4,
+            #[cfg(feature = "snappy")]
+            CompressionCodec::Snappy,
+        ]
+    }
+
+    #[test]
+    fn test_sort_and_compress_message() {
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        for codec in codecs() {
+            let compressed = sort_and_compress_protobuf_message(&msg, codec).unwrap();
+            assert_eq!(
+                decompress_and_sort_protobuf_message(&compressed, codec).unwrap(),
+                [0x08, 0x01, 0x10, 0x02]
+            );
+            assert!(matches!(
+                sort_and_compress_protobuf_message(&[0x0F], codec),
+                Err(CompressError::Parse(_))
+            ));
+            assert!(matches!(
+                decompress_and_sort_protobuf_message(&[0xff; 4], codec),
+                Err(DecompressOrParseError::Decompress(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{read_varint_scalar, varint_fits_u64, ParseError, MAX_FIELD_ID};
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// at compile time, e.g. to embed canonical message literals as constants.
+///
+/// Uses insertion sort without heap allocations, so it's only suitable for small messages.
+pub const fn sort_protobuf_message_const<const N: usize>(msg: &[u8; N]) -> Result<[u8; N], ParseError> {
+    let bytes: &[u8] = msg;
+    // Field boundaries and IDs, in the original order
+    let (mut starts, mut ends, mut ids) = ([0usize; N], [0usize; N], [0u64; N]);
+    let (mut count, mut offset) = (0, 0);
+    while offset < N {
+        let (key, len) = match read_varint_scalar(bytes.split_at(offset).1) {
+            Ok((key, len)) if varint_fits_u64(key) => (key, len),
+            _ => return Err(ParseError),
+        };
+        let (field_id, wire_type) = (key >> 3, key & 0x7);
+        if field_id == 0 || field_id > MAX_FIELD_ID as u128 {
+            return Err(ParseError);
+        }
+        let mut end = offset + len;
+        match wire_type {
+            0 | 2 => {
+                if end > N {
+                    return Err(ParseError);
+                }
+                let (value, len) = match read_varint_scalar(bytes.split_at(end).1) {
+                    Ok((value, len)) if varint_fits_u64(value) => (value, len),
+                    _ => return Err(ParseError),
+                };
+                end += len;
+                if wire_type == 2 {
+                    if value > (N - end) as u128 {
+                        return Err(ParseError);
+                    }
+                    end += value as usize;
+                }
+            }
+            1 => end += 8,
+            5 => end += 4,
+            _ => return Err(ParseError),
+        }
+        if end > N {
+            return Err(ParseError);
+        }
+        (starts[count], ends[count], ids[count]) = (offset, end, field_id as u64);
+        count += 1;
+        offset = end;
+    }
+    // Stable insertion sort of field indices by ID
+    let mut order = [0usize; N];
+    let mut i = 0;
+    while i < count {
+        let mut j = i;
+        while j > 0 && ids[order[j - 1]] > ids[i] {
+            order[j] = order[j - 1];
+            j -= 1;
+        }
+        order[j] = i;
+        i += 1;
+    }
+    let mut sorted = [0u8; N];
+    let (mut i, mut pos) = (0, 0);
+    while i < count {
+        let mut k = starts[order[i]];
+        while k < ends[order[i]] {
+            sorted[pos] = msg[k];
+            (pos, k) = (pos + 1, k + 1);
+        }
+        i += 1;
+    }
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_protobuf_message_const;
+    use crate::sort_protobuf_message;
+
+    // Fields 3, 1, 2, 1
+    const SORTED: [u8; 13] = match sort_protobuf_message_const(&[
+        0x1a, 0x03, b'a', b'b', b'c', 0x08, 0x96, 0x01, 0x15, 0x01, 0x02, 0x03, 0x04,
+    ]) {
+        Ok(sorted) => sorted,
+        Err(_) => panic!("invalid message"),
+    };
+
+    #[test]
+    fn test_sort_message_const() {
+        assert_eq!(
+            SORTED,
+            [0x08, 0x96, 0x01, 0x15, 0x01, 0x02, 0x03, 0x04, 0x1a, 0x03, b'a', b'b', b'c']
+        );
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x18, 0x04, 0x08, 0x02];
+        assert_eq!(
+            sort_protobuf_message_const(&msg).unwrap(),
+            sort_protobuf_message(&msg).unwrap().as_ref()
+        );
+        assert_eq!(sort_protobuf_message_const(&[]).unwrap(), [0u8; 0]);
+        for invalid in [[0x0F, 0x00], [0x08, 0x80], [0x0a, 0x01], [0x00, 0x01], [0x09, 0x01]] {
+            assert!(sort_protobuf_message_const(&invalid).is_err());
+            assert!(sort_protobuf_message(&invalid).is_err());
+        }
+        // Varint value of 2^64
+        let too_big = [0x08, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02];
+        assert!(sort_protobuf_message_const(&too_big).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{sorted_chunks, write_varint, ParseError};
+use thiserror::Error;
+
+/// Failure of a decryption callback, see [`sort_protobuf_message_with_encryption`].
+#[derive(Debug, Clone, Error)]
+#[error("failed to decrypt protobuf field payload")]
+pub struct DecryptError;
+
+#[derive(Debug, Clone, Error)]
+pub enum CryptoSortError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("field {field_id}: {source}")]
+    Decrypt {
+        field_id: u32,
+        #[source]
+        source: DecryptError,
+    },
+    #[error("field {field_id} is not length-delimited, its payload can't be encrypted")]
+    NotLengthDelimited { field_id: u32 },
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// decrypting payloads of the length-delimited field `field_id` and encrypting them again
+/// in the sorted output, e.g. to refresh nonces. Length prefixes are updated to match.
+///
+/// Fails with [`CryptoSortError::NotLengthDelimited`] if the field is not length-delimited.
+pub fn sort_protobuf_message_with_encryption<E, D>(
+    msg: &[u8],
+    field_id: u32,
+    encrypt: E,
+    decrypt: D,
+) -> Result<Vec<u8>, CryptoSortError>
+where
+    E: Fn(&[u8]) -> Vec<u8>,
+    D: Fn(&[u8]) -> Result<Vec<u8>, DecryptError>,
+{
+    let chunks = sorted_chunks(msg)?;
+    let mut sorted = Vec::with_capacity(msg.len());
+    for ck in &chunks {
+        if ck.id != field_id {
+            sorted.extend_from_slice(ck.bytes(msg));
+            continue;
+        }
+        if ck.wire_type != 2 {
+            // Only bytes can be encrypted
+            return Err(CryptoSortError::NotLengthDelimited { field_id });
+        }
+        let plaintext = decrypt(ck.payload(msg)).map_err(|source| CryptoSortError::Decrypt { field_id, source })?;
+        let ciphertext = encrypt(&plaintext);
+        sorted.extend_from_slice(&msg[ck.offset..ck.offset + ck.tag_length(msg)]);
+        write_varint(ciphertext.len() as u128, &mut sorted);
+        sorted.extend_from_slice(&ciphertext);
+    }
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_with_encryption, CryptoSortError, DecryptError};
+
+    #[test]
+    fn test_sort_message_with_encryption() {
+        // "Encryption" appends a checksum byte
+        let encrypt = |plain: &[u8]| [plain, &[plain.len() as u8]].concat();
+        let decrypt = |cipher: &[u8]| match cipher.split_last() {
+            Some((&len, plain)) if plain.len() == len as usize => Ok(plain.to_vec()),
+            _ => Err(DecryptError),
+        };
+        let decrypt_and_double = |cipher: &[u8]| decrypt(cipher).map(|plain| plain.repeat(2));
+        // Fields 2 ("ab" encrypted), 1
+        let msg = [0x12, 0x03, b'a', b'b', 0x02, 0x08, 0x01];
+        assert_eq!(
+            sort_protobuf_message_with_encryption(&msg, 2, encrypt, decrypt).unwrap(),
+            [0x08, 0x01, 0x12, 0x03, b'a', b'b', 0x02]
+        );
+        assert_eq!(
+            sort_protobuf_message_with_encryption(&msg, 2, encrypt, decrypt_and_double).unwrap(),
+            [0x08, 0x01, 0x12, 0x05, b'a', b'b', b'a', b'b', 0x04]
+        );
+        assert!(matches!(
+            sort_protobuf_message_with_encryption(&[0x12, 0x01, 0x05], 2, encrypt, decrypt),
+            Err(CryptoSortError::Decrypt { field_id: 2, .. })
+        ));
+        assert!(matches!(
+            sort_protobuf_message_with_encryption(&msg, 1, encrypt, decrypt),
+            Err(CryptoSortError::NotLengthDelimited { field_id: 1 })
+        ));
+        assert!(matches!(
+            sort_protobuf_message_with_encryption(&[0x0F], 2, encrypt, decrypt),
+            Err(CryptoSortError::Parse(_))
+        ));
+    }
+}
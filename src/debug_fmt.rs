@@ -0,0 +1,60 @@
+use crate::{parse_message, ParseError};
+use std::fmt::Write;
+
+/// Dump the given protobuf message field-by-field, one line per field, e.g.:
+/// ```text
+/// [0] field_id=1 wire_type=0 (Varint) payload=54
+/// [1] field_id=3 wire_type=2 (LenDelim) payload=32 bytes
+/// ```
+/// Scalar payloads are shown as hex, length-delimited ones as their size.
+/// The format is stable, so the output can be used in test snapshots.
+pub fn message_to_debug_string(msg: &[u8]) -> Result<String, ParseError> {
+    let chunks = parse_message(msg)?;
+    let mut out = String::new();
+    for (i, ck) in chunks.iter().enumerate() {
+        let name = match ck.wire_type {
+            0 => "Varint",
+            1 => "Fixed64",
+            2 => "LenDelim",
+            _ => "Fixed32",
+        };
+        let _ = write!(
+            out,
+            "[{}] field_id={} wire_type={} ({}) payload=",
+            i, ck.id, ck.wire_type, name
+        );
+        let payload = ck.payload(msg);
+        if ck.wire_type == 2 {
+            let _ = write!(out, "{} bytes", payload.len());
+        } else {
+            for byte in payload {
+                let _ = write!(out, "{:02x}", byte);
+            }
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::message_to_debug_string;
+
+    #[test]
+    fn test_message_to_debug_string() {
+        assert_eq!(message_to_debug_string(&[]).unwrap(), "");
+        let msg = [
+            0x08, 0x54, 0x1a, 0x02, 0xab, 0xcd, 0x21, 1, 2, 3, 4, 5, 6, 7, 8, 0x2d, 0xff, 0, 0, 0,
+        ];
+        assert_eq!(
+            message_to_debug_string(&msg).unwrap(),
+            concat!(
+                "[0] field_id=1 wire_type=0 (Varint) payload=54\n",
+                "[1] field_id=3 wire_type=2 (LenDelim) payload=2 bytes\n",
+                "[2] field_id=4 wire_type=1 (Fixed64) payload=0102030405060708\n",
+                "[3] field_id=5 wire_type=5 (Fixed32) payload=ff000000\n",
+            )
+        );
+        assert!(message_to_debug_string(&[0x0F]).is_err());
+    }
+}
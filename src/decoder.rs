@@ -0,0 +1,139 @@
+use crate::{parse_field, read_varint, ParseError};
+
+/// Decodes typed values from a raw field payload, i.e. field bytes without
+/// the tag and (for length-delimited fields) without the length prefix.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldDecoder<'a>(pub &'a [u8]);
+
+impl<'a> FieldDecoder<'a> {
+    /// Decodes the payload of a varint field (wire type 0).
+    pub fn decode_varint(&self) -> Result<u64, ParseError> {
+        let (value, len) = read_varint(self.0)?;
+        if len != self.0.len() {
+            // Trailing bytes after the varint
+            return Err(ParseError);
+        }
+        u64::try_from(value).map_err(|_| ParseError)
+    }
+
+    /// Decodes the payload of a `sint32` field (zigzag-encoded varint).
+    pub fn decode_sint32(&self) -> Result<i32, ParseError> {
+        let value = u32::try_from(self.decode_varint()?).map_err(|_| ParseError)?;
+        Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+    }
+
+    /// Decodes the payload of a `sint64` field (zigzag-encoded varint).
+    pub fn decode_sint64(&self) -> Result<i64, ParseError> {
+        let value = self.decode_varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Decodes the payload of a 32-bit field (wire type 5).
+    pub fn decode_fixed32(&self) -> Result<u32, ParseError> {
+        let bytes = <[u8; 4]>::try_from(self.0).map_err(|_| ParseError)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Decodes the payload of a 64-bit field (wire type 1).
+    pub fn decode_fixed64(&self) -> Result<u64, ParseError> {
+        let bytes = <[u8; 8]>::try_from(self.0).map_err(|_| ParseError)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Returns the payload of a length-delimited field (wire type 2) as is.
+    pub fn decode_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Decodes the payload of a length-delimited field (wire type 2) as UTF-8 string.
+    pub fn decode_string(&self) -> Result<&'a str, ParseError> {
+        std::str::from_utf8(self.0).map_err(|_| ParseError)
+    }
+}
+
+/// Strips the tag and the length prefix from the raw bytes of a single length-delimited field.
+pub fn decode_length_delimited_payload(field_bytes: &[u8]) -> Result<&[u8], ParseError> {
+    let ck = parse_field(field_bytes, 0)?;
+    if ck.wire_type != 2 || ck.length != field_bytes.len() {
+        return Err(ParseError);
+    }
+    Ok(ck.payload(field_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_length_delimited_payload, FieldDecoder};
+
+    #[test]
+    fn test_decode_varint() {
+        assert_eq!(FieldDecoder(&[0x54]).decode_varint().unwrap(), 0x54);
+        assert_eq!(FieldDecoder(&[0x90, 0x4e]).decode_varint().unwrap(), 10000);
+        assert_eq!(
+            FieldDecoder(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01])
+                .decode_varint()
+                .unwrap(),
+            u64::MAX
+        );
+        assert!(FieldDecoder(&[]).decode_varint().is_err());
+        assert!(FieldDecoder(&[0x01, 0x02]).decode_varint().is_err());
+    }
+
+    #[test]
+    fn test_decode_zigzag() {
+        assert_eq!(FieldDecoder(&[0x00]).decode_sint32().unwrap(), 0);
+        assert_eq!(FieldDecoder(&[0x01]).decode_sint32().unwrap(), -1);
+        assert_eq!(FieldDecoder(&[0x02]).decode_sint32().unwrap(), 1);
+        assert_eq!(
+            FieldDecoder(&[0xfe, 0xff, 0xff, 0xff, 0x0f]).decode_sint32().unwrap(),
+            i32::MAX
+        );
+        assert_eq!(
+            FieldDecoder(&[0xff, 0xff, 0xff, 0xff, 0x0f]).decode_sint32().unwrap(),
+            i32::MIN
+        );
+        assert!(FieldDecoder(&[0x80, 0x80, 0x80, 0x80, 0x10]).decode_sint32().is_err());
+        assert_eq!(FieldDecoder(&[0x03]).decode_sint64().unwrap(), -2);
+        assert_eq!(
+            FieldDecoder(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01])
+                .decode_sint64()
+                .unwrap(),
+            i64::MIN
+        );
+    }
+
+    #[test]
+    fn test_decode_fixed() {
+        assert_eq!(
+            FieldDecoder(&[0x01, 0x02, 0x03, 0x04]).decode_fixed32().unwrap(),
+            0x04030201
+        );
+        assert!(FieldDecoder(&[0x01, 0x02, 0x03]).decode_fixed32().is_err());
+        assert_eq!(
+            FieldDecoder(&[1, 0, 0, 0, 0, 0, 0, 0x80]).decode_fixed64().unwrap(),
+            0x8000000000000001
+        );
+        assert!(FieldDecoder(&[0x01, 0x02, 0x03, 0x04]).decode_fixed64().is_err());
+    }
+
+    #[test]
+    fn test_decode_bytes_and_string() {
+        assert_eq!(FieldDecoder(b"abc").decode_bytes(), b"abc");
+        assert_eq!(FieldDecoder(b"abc").decode_string().unwrap(), "abc");
+        assert!(FieldDecoder(&[0xff, 0xfe]).decode_string().is_err());
+    }
+
+    #[test]
+    fn test_decode_length_delimited_payload() {
+        assert_eq!(
+            decode_length_delimited_payload(&[0x0a, 0x03, b'a', b'b', b'c']).unwrap(),
+            b"abc"
+        );
+        assert_eq!(decode_length_delimited_payload(&[0x82, 0x01, 0x00]).unwrap(), b"");
+        // Not a length-delimited field
+        assert!(decode_length_delimited_payload(&[0x08, 0x01]).is_err());
+        // Truncated payload and trailing bytes
+        assert!(decode_length_delimited_payload(&[0x0a, 0x03, b'a']).is_err());
+        assert!(decode_length_delimited_payload(&[0x0a, 0x01, b'a', b'b']).is_err());
+        assert!(decode_length_delimited_payload(&[]).is_err());
+    }
+}
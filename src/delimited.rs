@@ -0,0 +1,39 @@
+use crate::{sort_protobuf_message, ParseError};
+
+/// Sort each message in a stream of messages framed with 4-byte big-endian length prefixes,
+/// i.e. `{u32_be_length}{proto_bytes}...`. The output keeps the same framing.
+pub fn sort_delimited_message_stream(input: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let mut result = Vec::with_capacity(input.len());
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (prefix, tail) = rest.split_at_checked(4).ok_or(ParseError)?;
+        let length = u32::from_be_bytes(prefix.try_into().map_err(|_| ParseError)?) as usize;
+        let (msg, tail) = tail.split_at_checked(length).ok_or(ParseError)?;
+        // Sorting keeps the message length intact, so the prefix is reused
+        result.extend_from_slice(prefix);
+        result.extend_from_slice(&sort_protobuf_message(msg)?);
+        rest = tail;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_delimited_message_stream;
+
+    #[test]
+    fn test_sort_delimited_message_stream() {
+        let input = [
+            0, 0, 0, 4, 0x10, 0x02, 0x08, 0x01, // Message 1
+            0, 0, 0, 0, // Empty message
+            0, 0, 0, 2, 0x18, 0x03, // Message 3
+        ];
+        let expected = [0, 0, 0, 4, 0x08, 0x01, 0x10, 0x02, 0, 0, 0, 0, 0, 0, 0, 2, 0x18, 0x03];
+        assert_eq!(sort_delimited_message_stream(&input).unwrap(), expected);
+        assert!(sort_delimited_message_stream(&[]).unwrap().is_empty());
+        // Truncated length prefix and message
+        assert!(sort_delimited_message_stream(&[0, 0, 0]).is_err());
+        assert!(sort_delimited_message_stream(&[0, 0, 0, 4, 0x08, 0x01]).is_err());
+        assert!(sort_delimited_message_stream(&[0, 0, 0, 1, 0x0F]).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{sort_protobuf_message, ParseError};
+use serde::de::DeserializeOwned;
+use std::error::Error as StdError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SortDeserError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("failed to deserialize sorted protobuf message")]
+    Deserialize(#[source] Box<dyn StdError + Send + Sync>),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID) and pass
+/// the sorted bytes to `deserializer_fn`, for parsers that rely on the fields order.
+pub fn sorted_message_from_serde<T, DeError>(
+    msg: &[u8],
+    deserializer_fn: impl Fn(&[u8]) -> Result<T, DeError>,
+) -> Result<T, SortDeserError>
+where
+    T: DeserializeOwned,
+    DeError: StdError + Send + Sync + 'static,
+{
+    let sorted = sort_protobuf_message(msg)?;
+    deserializer_fn(&sorted).map_err(|err| SortDeserError::Deserialize(Box::new(err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sorted_message_from_serde, SortDeserError};
+
+    #[test]
+    fn test_sorted_message_from_serde() {
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        let sorted = sorted_message_from_serde(&msg, |bytes| Ok::<_, std::fmt::Error>(bytes.to_vec()));
+        assert_eq!(sorted.unwrap(), [0x08, 0x01, 0x10, 0x02]);
+        assert!(matches!(
+            sorted_message_from_serde(&msg, |bytes| serde_json::from_slice::<Vec<u8>>(bytes)),
+            Err(SortDeserError::Deserialize(_))
+        ));
+        assert!(matches!(
+            sorted_message_from_serde(&[0x0F], |bytes| serde_json::from_slice::<Vec<u8>>(bytes)),
+            Err(SortDeserError::Parse(_))
+        ));
+    }
+}
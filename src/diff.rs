@@ -0,0 +1,225 @@
+use crate::{parse_message, read_varint, write_varint, ParseError};
+use std::collections::BTreeMap;
+
+/// Change of all the occurrences of a single field, as raw field bytes including the tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field_id: u32,
+    /// Field occurrences in the original message, empty if the field was added
+    pub old: Vec<Vec<u8>>,
+    /// Field occurrences in the target message, empty if the field was removed
+    pub new: Vec<Vec<u8>>,
+}
+
+/// Difference between two protobuf messages, regardless of their fields order,
+/// see [`diff_protobuf_messages`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageDiff {
+    changes: Vec<FieldChange>,
+}
+
+impl MessageDiff {
+    /// Changed fields, ordered by field ID.
+    pub fn changes(&self) -> &[FieldChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Apply the diff to the given message: the changed fields are replaced with their
+    /// target occurrences. The result is sorted by field ID.
+    pub fn apply(&self, base: &[u8]) -> Result<Vec<u8>, ParseError> {
+        let mut fields = fields_by_id(base)?;
+        for change in &self.changes {
+            if change.new.is_empty() {
+                fields.remove(&change.field_id);
+            } else {
+                fields.insert(change.field_id, change.new.iter().map(Vec::as_slice).collect());
+            }
+        }
+        Ok(fields.into_values().flatten().flatten().copied().collect())
+    }
+
+    /// Diff that undoes this one, i.e. transforms the target message back to the original.
+    pub fn invert(&self) -> MessageDiff {
+        let changes = self.changes.iter().map(|change| FieldChange {
+            field_id: change.field_id,
+            old: change.new.clone(),
+            new: change.old.clone(),
+        });
+        MessageDiff {
+            changes: changes.collect(),
+        }
+    }
+
+    /// Encode the diff as a protobuf message: a changed or added field becomes a length-delimited
+    /// field with the same ID containing its target occurrences, a removed one becomes a varint 0.
+    ///
+    /// Original occurrences are not encoded, so the decoded diff can be applied but not inverted.
+    pub fn to_patch_bytes(&self) -> Vec<u8> {
+        let mut patch = Vec::new();
+        for change in &self.changes {
+            let field_id = u128::from(change.field_id);
+            if change.new.is_empty() {
+                write_varint(field_id << 3, &mut patch);
+                patch.push(0);
+            } else {
+                write_varint(field_id << 3 | 2, &mut patch);
+                write_varint(change.new.iter().map(Vec::len).sum::<usize>() as u128, &mut patch);
+                change.new.iter().for_each(|bytes| patch.extend_from_slice(bytes));
+            }
+        }
+        patch
+    }
+
+    /// Decode a diff encoded by [`MessageDiff::to_patch_bytes`], with no original occurrences.
+    pub fn from_patch_bytes(patch: &[u8]) -> Result<MessageDiff, ParseError> {
+        let mut changes: Vec<FieldChange> = Vec::new();
+        for ck in &parse_message(patch)? {
+            let field_id = ck.id;
+            if changes.last().is_some_and(|change| change.field_id >= field_id) {
+                // Changes must be ordered by field ID, without duplicates
+                return Err(ParseError);
+            }
+            let payload = ck.payload(patch);
+            let new = match ck.wire_type {
+                0 if read_varint(payload)?.0 == 0 => Vec::new(),
+                2 => {
+                    let occurrences = parse_message(payload)?;
+                    if occurrences.is_empty() || occurrences.iter().any(|occurrence| occurrence.id != ck.id) {
+                        return Err(ParseError);
+                    }
+                    occurrences
+                        .iter()
+                        .map(|occurrence| occurrence.bytes(payload).to_vec())
+                        .collect()
+                }
+                _ => return Err(ParseError),
+            };
+            changes.push(FieldChange {
+                field_id,
+                old: Vec::new(),
+                new,
+            });
+        }
+        Ok(MessageDiff { changes })
+    }
+}
+
+/// Compare fields of two protobuf messages by field ID. Fields with the same occurrences,
+/// in the same relative order, are considered equal even if the messages order them differently.
+pub fn diff_protobuf_messages(a: &[u8], b: &[u8]) -> Result<MessageDiff, ParseError> {
+    let (old_fields, mut new_fields) = (fields_by_id(a)?, fields_by_id(b)?);
+    let mut changes = BTreeMap::new();
+    for (field_id, old) in old_fields {
+        let new = new_fields.remove(&field_id).unwrap_or_default();
+        if old != new {
+            changes.insert(field_id, (old, new));
+        }
+    }
+    for (field_id, new) in new_fields {
+        changes.insert(field_id, (Vec::new(), new));
+    }
+    let to_vecs = |fields: Vec<&[u8]>| fields.into_iter().map(<[u8]>::to_vec).collect();
+    let changes = changes.into_iter().map(|(field_id, (old, new))| FieldChange {
+        field_id,
+        old: to_vecs(old),
+        new: to_vecs(new),
+    });
+    Ok(MessageDiff {
+        changes: changes.collect(),
+    })
+}
+
+/// Raw bytes of all the field occurrences, grouped by field ID
+fn fields_by_id(msg: &[u8]) -> Result<BTreeMap<u32, Vec<&[u8]>>, ParseError> {
+    let mut fields: BTreeMap<u32, Vec<&[u8]>> = BTreeMap::new();
+    for ck in &parse_message(msg)? {
+        let field_id = ck.id;
+        fields.entry(field_id).or_default().push(ck.bytes(msg));
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_protobuf_messages, FieldChange, MessageDiff};
+
+    #[test]
+    fn test_diff_messages() {
+        // Fields 1, 2, 3 vs 3, 1, 4 with field 1 changed
+        let a = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03];
+        let b = [0x18, 0x03, 0x08, 0x05, 0x20, 0x04];
+        let diff = diff_protobuf_messages(&a, &b).unwrap();
+        assert_eq!(
+            diff.changes(),
+            [
+                FieldChange {
+                    field_id: 1,
+                    old: vec![vec![0x08, 0x01]],
+                    new: vec![vec![0x08, 0x05]]
+                },
+                FieldChange {
+                    field_id: 2,
+                    old: vec![vec![0x10, 0x02]],
+                    new: vec![]
+                },
+                FieldChange {
+                    field_id: 4,
+                    old: vec![],
+                    new: vec![vec![0x20, 0x04]]
+                },
+            ]
+        );
+        assert!(diff_protobuf_messages(&a, &[0x18, 0x03, 0x10, 0x02, 0x08, 0x01])
+            .unwrap()
+            .is_empty());
+        assert!(diff_protobuf_messages(&a, &[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_apply_and_invert_diff() {
+        let a = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03, 0x18, 0x04];
+        let b = [0x18, 0x04, 0x18, 0x03, 0x08, 0x05, 0x20, 0x04];
+        let diff = diff_protobuf_messages(&a, &b).unwrap();
+        assert_eq!(
+            diff.apply(&a).unwrap(),
+            [0x08, 0x05, 0x18, 0x04, 0x18, 0x03, 0x20, 0x04]
+        );
+        assert_eq!(diff.invert().apply(&b).unwrap(), a);
+        assert_eq!(diff.invert().invert(), diff);
+        assert!(diff.apply(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_diff_patch_bytes() {
+        // Field 1 changed, field 2 removed, field 3 added twice
+        let a = [0x08, 0x01, 0x10, 0x02];
+        let b = [0x18, 0x03, 0x08, 0x05, 0x18, 0x04];
+        let diff = diff_protobuf_messages(&a, &b).unwrap();
+        let patch = diff.to_patch_bytes();
+        assert_eq!(
+            patch,
+            [0x0a, 0x02, 0x08, 0x05, 0x10, 0x00, 0x1a, 0x04, 0x18, 0x03, 0x18, 0x04]
+        );
+        let decoded = MessageDiff::from_patch_bytes(&patch).unwrap();
+        assert_eq!(decoded.apply(&a).unwrap(), diff.apply(&a).unwrap());
+        assert!(decoded.changes().iter().all(|change| change.old.is_empty()));
+        assert!(MessageDiff::from_patch_bytes(&[]).unwrap().is_empty());
+        for patch in [
+            [0x0F].as_slice(),
+            // Removal with a nonzero value
+            &[0x10, 0x01],
+            // Occurrence of another field
+            &[0x0a, 0x02, 0x10, 0x05],
+            &[0x0a, 0x00],
+            // Unordered changes
+            &[0x10, 0x00, 0x08, 0x00],
+            &[0x0d, 0, 0, 0, 0],
+        ] {
+            assert!(MessageDiff::from_patch_bytes(patch).is_err(), "{:02x?}", patch);
+        }
+    }
+}
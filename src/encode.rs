@@ -0,0 +1,66 @@
+use crate::{write_varint, MAX_FIELD_ID};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum EncodeError {
+    #[error("invalid protobuf field ID {0}")]
+    InvalidFieldId(u32),
+    #[error("payload of protobuf field {0} doesn't match its wire type")]
+    InvalidPayload(u32),
+}
+
+/// Append the tag of the field with the given ID and wire type to the buffer
+pub(crate) fn encode_tag(field_id: u32, wire_type: u8, buf: &mut Vec<u8>) -> Result<(), EncodeError> {
+    if field_id == 0 || u64::from(field_id) > MAX_FIELD_ID {
+        return Err(EncodeError::InvalidFieldId(field_id));
+    }
+    write_varint(u128::from(field_id) << 3 | u128::from(wire_type), buf);
+    Ok(())
+}
+
+/// Encode a length-delimited field (wire type 2): the tag, the length prefix and the payload.
+pub fn encode_length_delimited_field(field_id: u32, payload: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    let mut buf = Vec::with_capacity(payload.len() + 10);
+    encode_tag(field_id, 2, &mut buf)?;
+    write_varint(payload.len() as u128, &mut buf);
+    buf.extend_from_slice(payload);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_length_delimited_field, encode_tag, EncodeError};
+
+    #[test]
+    fn test_encode_tag() {
+        let test = |field_id: u32, wire_type: u8| {
+            let mut buf = Vec::new();
+            encode_tag(field_id, wire_type, &mut buf).map(|_| buf)
+        };
+        assert_eq!(test(1, 0).unwrap(), [0x08]);
+        assert_eq!(test(3, 2).unwrap(), [0x1a]);
+        assert_eq!(test(16, 0).unwrap(), [0x80, 0x01]);
+        assert_eq!(test(536_870_911, 5).unwrap(), [0xfd, 0xff, 0xff, 0xff, 0x0f]);
+        assert!(matches!(test(0, 0), Err(EncodeError::InvalidFieldId(0))));
+        assert!(matches!(
+            test(536_870_912, 0),
+            Err(EncodeError::InvalidFieldId(536_870_912))
+        ));
+    }
+
+    #[test]
+    fn test_encode_length_delimited_field() {
+        assert_eq!(
+            encode_length_delimited_field(1, b"abc").unwrap(),
+            [0x0a, 0x03, b'a', b'b', b'c']
+        );
+        assert_eq!(encode_length_delimited_field(16, &[]).unwrap(), [0x82, 0x01, 0x00]);
+        let long = encode_length_delimited_field(2, &[0xff; 300]).unwrap();
+        assert_eq!(long[..3], [0x12, 0xac, 0x02]);
+        assert_eq!(long.len(), 303);
+        assert!(matches!(
+            encode_length_delimited_field(0, b"abc"),
+            Err(EncodeError::InvalidFieldId(0))
+        ));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{parse_field, parse_message, read_varint, Chunk, ParseError};
+use std::fmt;
+
+/// Protobuf wire type, the lower 3 bits of a field tag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WireType {
+    #[default]
+    Varint = 0,
+    Fixed64 = 1,
+    LengthDelimited = 2,
+    Fixed32 = 5,
+}
+
+impl WireType {
+    /// Checks if the given raw payload is a valid value of this wire type.
+    pub(crate) fn is_valid_payload(self, payload: &[u8]) -> bool {
+        match self {
+            WireType::Varint => matches!(read_varint(payload), Ok((_, len)) if len == payload.len()),
+            WireType::Fixed64 => payload.len() == 8,
+            WireType::LengthDelimited => true,
+            WireType::Fixed32 => payload.len() == 4,
+        }
+    }
+}
+
+impl TryFrom<u8> for WireType {
+    type Error = ParseError;
+
+    /// Deprecated group wire types (3 and 4) are not supported.
+    fn try_from(value: u8) -> Result<Self, ParseError> {
+        match value {
+            0 => Ok(WireType::Varint),
+            1 => Ok(WireType::Fixed64),
+            2 => Ok(WireType::LengthDelimited),
+            5 => Ok(WireType::Fixed32),
+            _ => Err(ParseError),
+        }
+    }
+}
+
+/// A single field of a parsed protobuf message.
+///
+/// The default value is a placeholder for [`parse_message_zero_copy`] buffers.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Field<'a> {
+    id: u32,
+    wire_type: WireType,
+    offset: usize,
+    header_length: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> Field<'a> {
+    pub(crate) fn from_chunk(ck: &Chunk, msg: &'a [u8]) -> Result<Self, ParseError> {
+        Ok(Field {
+            id: ck.id,
+            wire_type: WireType::try_from(ck.wire_type)?,
+            offset: ck.offset,
+            header_length: ck.header_length,
+            bytes: ck.bytes(msg),
+        })
+    }
+
+    /// The reverse of [`Field::from_chunk`], the chunk points into the message the field was parsed from.
+    pub(crate) fn to_chunk(self) -> Chunk {
+        Chunk {
+            id: self.id,
+            wire_type: self.wire_type as u8,
+            offset: self.offset,
+            header_length: self.header_length,
+            length: self.bytes.len(),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn wire_type(&self) -> WireType {
+        self.wire_type
+    }
+
+    /// Offset of the field in the message.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Raw field bytes, including the tag.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Field value bytes, without the tag and the length prefix.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.bytes[self.header_length..]
+    }
+}
+
+impl fmt::Display for Field<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Field {{ id: {}, wire_type: {:?}, length: {} }}",
+            self.id,
+            self.wire_type,
+            self.payload().len()
+        )
+    }
+}
+
+/// Same as `Display`, but in debug builds also shows the first 16 bytes of payload as hex.
+impl fmt::Debug for Field<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Field");
+        s.field("id", &self.id)
+            .field("wire_type", &self.wire_type)
+            .field("length", &self.payload().len());
+        #[cfg(debug_assertions)]
+        s.field("payload", &PayloadHex(self.payload(), 16));
+        s.finish()
+    }
+}
+
+/// Detailed `Debug` output of a field, see [`debug_field`].
+#[derive(Clone, Copy)]
+pub struct FieldDebug<'a> {
+    field: &'a Field<'a>,
+}
+
+/// Show the field ID, wire type, offset, payload length and the first 32 bytes of payload
+/// as hex in `Debug` output, regardless of the build profile.
+pub fn debug_field<'a>(field: &'a Field<'a>) -> FieldDebug<'a> {
+    FieldDebug { field }
+}
+
+impl fmt::Debug for FieldDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("field_id", &self.field.id)
+            .field("wire_type", &self.field.wire_type)
+            .field("offset", &self.field.offset)
+            .field("length", &self.field.payload().len())
+            .field("payload_hex", &PayloadHex(self.field.payload(), 32))
+            .finish()
+    }
+}
+
+/// Payload bytes as hex, up to the given number of bytes
+struct PayloadHex<'a>(&'a [u8], usize);
+
+impl fmt::Debug for PayloadHex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter().take(self.1) {
+            write!(f, "{:02x}", byte)?;
+        }
+        if self.0.len() > self.1 {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+
+/// Serialized as `{"field_id": 1, "wire_type": "Varint", "payload": "9601"}`,
+/// with the payload (without the tag and the length prefix) as hex.
+///
+/// There is no `Deserialize`, since a field borrows its bytes from the message.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Field<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut s = serializer.serialize_struct("Field", 3)?;
+        s.serialize_field("field_id", &self.id)?;
+        s.serialize_field("wire_type", &self.wire_type)?;
+        s.serialize_field("payload", &crate::hex::encode_hex(self.payload()))?;
+        s.end()
+    }
+}
+
+/// Parse the given protobuf message into the list of its fields, in the message order.
+pub fn parse_fields(msg: &[u8]) -> Result<Vec<Field<'_>>, ParseError> {
+    let chunks = parse_message(msg)?;
+    chunks.iter().map(|ck| Field::from_chunk(ck, msg)).collect()
+}
+
+/// Parse the given protobuf message into the caller-provided buffer, without allocations.
+///
+/// Returns the number of fields written to `fields`, fails if the message has more fields
+/// than the buffer can hold.
+pub fn parse_message_zero_copy<'a>(msg: &'a [u8], fields: &mut [Field<'a>]) -> Result<usize, ParseError> {
+    let mut offset = 0;
+    let mut count = 0;
+    while offset < msg.len() {
+        let ck = parse_field(msg, offset)?;
+        let slot = fields.get_mut(count).ok_or(ParseError)?;
+        *slot = Field::from_chunk(&ck, msg)?;
+        offset += ck.length;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{debug_field, parse_fields, parse_message_zero_copy, Field, WireType};
+
+    #[test]
+    fn test_parse_fields() {
+        let msg = [0x18, 0x96, 0x01, 0x0a, 0x02, 0xab, 0xcd, 0x15, 1, 2, 3, 4];
+        let fields = parse_fields(&msg).unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!((fields[0].id(), fields[0].wire_type()), (3, WireType::Varint));
+        assert_eq!(fields[0].payload(), [0x96, 0x01]);
+        assert_eq!((fields[1].id(), fields[1].wire_type()), (1, WireType::LengthDelimited));
+        assert_eq!(fields[1].offset(), 3);
+        assert_eq!(fields[1].bytes(), [0x0a, 0x02, 0xab, 0xcd]);
+        assert_eq!(fields[1].payload(), [0xab, 0xcd]);
+        assert_eq!((fields[2].id(), fields[2].wire_type()), (2, WireType::Fixed32));
+        assert!(parse_fields(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_zero_copy() {
+        let msg = [0x18, 0x96, 0x01, 0x0a, 0x02, 0xab, 0xcd, 0x15, 1, 2, 3, 4];
+        let mut fields = [Field::default(); 4];
+        assert_eq!(parse_message_zero_copy(&msg, &mut fields).unwrap(), 3);
+        assert_eq!(fields[..3], parse_fields(&msg).unwrap()[..]);
+        assert_eq!(fields[3], Field::default());
+        assert!(parse_message_zero_copy(&msg, &mut fields[..2]).is_err());
+        assert_eq!(parse_message_zero_copy(&[], &mut []).unwrap(), 0);
+        assert!(parse_message_zero_copy(&[0x0F], &mut fields).is_err());
+    }
+
+    #[test]
+    fn test_field_display() {
+        let msg = [0x0a, 0x12, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17];
+        let fields = parse_fields(&msg).unwrap();
+        assert_eq!(
+            fields[0].to_string(),
+            "Field { id: 1, wire_type: LengthDelimited, length: 18 }"
+        );
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            format!("{:?}", fields[0]),
+            "Field { id: 1, wire_type: LengthDelimited, length: 18, payload: 000102030405060708090a0b0c0d0e0f... }"
+        );
+    }
+
+    #[test]
+    fn test_debug_field() {
+        let mut msg = vec![0x08, 0x01, 0x12, 0x22];
+        msg.extend(0..0x22);
+        let fields = parse_fields(&msg).unwrap();
+        assert_eq!(
+            format!("{:?}", debug_field(&fields[1])),
+            concat!(
+                "Field { field_id: 2, wire_type: LengthDelimited, offset: 2, length: 34, ",
+                "payload_hex: 000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f... }"
+            )
+        );
+        assert_eq!(
+            format!("{:?}", debug_field(&fields[0])),
+            "Field { field_id: 1, wire_type: Varint, offset: 0, length: 1, payload_hex: 01 }"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_field_serialize() {
+        let msg = [0x08, 0x96, 0x01, 0x12, 0x02, 0xab, 0xcd];
+        let json = serde_json::to_value(parse_fields(&msg).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"field_id": 1, "wire_type": "Varint", "payload": "9601"},
+                {"field_id": 2, "wire_type": "LengthDelimited", "payload": "abcd"},
+            ])
+        );
+        let wire_type: WireType = serde_json::from_value(json[1]["wire_type"].clone()).unwrap();
+        assert_eq!(wire_type, WireType::LengthDelimited);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{parse_message, ParseError};
+use std::collections::BTreeSet;
+
+/// Set of field IDs present in a protobuf message.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageFieldSet(BTreeSet<u32>);
+
+impl MessageFieldSet {
+    /// Collect IDs of all the fields of the given protobuf message.
+    pub fn from_message(msg: &[u8]) -> Result<MessageFieldSet, ParseError> {
+        let chunks = parse_message(msg)?;
+        Ok(MessageFieldSet(chunks.iter().map(|ck| ck.id).collect()))
+    }
+
+    pub fn from_field_ids(ids: impl IntoIterator<Item = u32>) -> MessageFieldSet {
+        MessageFieldSet(ids.into_iter().collect())
+    }
+
+    pub fn contains(&self, field_id: u32) -> bool {
+        self.0.contains(&field_id)
+    }
+
+    pub fn union(&self, other: &MessageFieldSet) -> MessageFieldSet {
+        MessageFieldSet(self.0.union(&other.0).copied().collect())
+    }
+
+    pub fn intersection(&self, other: &MessageFieldSet) -> MessageFieldSet {
+        MessageFieldSet(self.0.intersection(&other.0).copied().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageFieldSet;
+
+    #[test]
+    fn test_message_field_set() {
+        // Fields 3, 1, 3
+        let a = MessageFieldSet::from_message(&[0x18, 0x03, 0x08, 0x01, 0x18, 0x04]).unwrap();
+        assert_eq!(a, MessageFieldSet::from_field_ids([1, 3]));
+        assert!(a.contains(1) && a.contains(3) && !a.contains(2));
+        let b = MessageFieldSet::from_field_ids([2, 3]);
+        assert_eq!(a.union(&b), MessageFieldSet::from_field_ids([1, 2, 3]));
+        assert_eq!(a.intersection(&b), MessageFieldSet::from_field_ids([3]));
+        assert_eq!(MessageFieldSet::from_message(&[]).unwrap(), MessageFieldSet::default());
+        assert!(MessageFieldSet::from_message(&[0x0F]).is_err());
+    }
+}
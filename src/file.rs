@@ -0,0 +1,176 @@
+use crate::{sort_protobuf_message, ParseError};
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SortFileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Error of [`sort_protobuf_file_atomic`]
+#[cfg(feature = "mmap")]
+pub type AtomicSortError = SortFileError;
+
+/// Sort fields of the protobuf message stored in `input_path` and write the result to `output_path`.
+///
+/// The output is written to a temporary file first and then renamed, so `output_path` may be
+/// the same as `input_path`. Returns `true` if the fields had to be reordered.
+///
+/// Paths may be anything convertible to [`Path`], including `camino::Utf8Path`.
+pub fn sort_protobuf_file(input_path: impl AsRef<Path>, output_path: impl AsRef<Path>) -> Result<bool, SortFileError> {
+    let (input_path, output_path) = (input_path.as_ref(), output_path.as_ref());
+    let msg = fs::read(input_path)?;
+    let sorted = sort_protobuf_message(&msg)?;
+    let reordered = matches!(sorted, Cow::Owned(_));
+    if !reordered && input_path == output_path {
+        return Ok(false);
+    }
+    replace_file(output_path, &sorted)?;
+    Ok(reordered)
+}
+
+/// Sort fields of the large protobuf message stored in the given file in place, reading it
+/// through a memory map. Either the sorted file replaces the original or the latter is untouched.
+#[cfg(feature = "mmap")]
+pub fn sort_protobuf_file_atomic(path: impl AsRef<Path>) -> Result<(), AtomicSortError> {
+    let path = path.as_ref();
+    let file = fs::File::open(path)?;
+    // SAFETY: the file is only replaced by renaming, not modified. Other processes truncating it
+    // in the meantime is undefined behavior, which the memory map can't protect against.
+    let msg = unsafe { memmap2::Mmap::map(&file)? };
+    if let Cow::Owned(sorted) = sort_protobuf_message(&msg)? {
+        replace_file(path, &sorted)?;
+    }
+    Ok(())
+}
+
+/// Write `contents` to a temporary file next to `path`, then rename it to `path`
+fn replace_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(".protofixer-tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    let result = fs::File::create(&tmp_path).and_then(|mut tmp| {
+        tmp.write_all(contents)?;
+        tmp.sync_all()
+    });
+    if let Err(err) = result.and_then(|()| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Sort fields of the protobuf message stored in the given file in place.
+///
+/// Returns `true` if the fields had to be reordered, otherwise the file is left untouched.
+pub fn sort_protobuf_file_in_place(path: impl AsRef<Path>) -> Result<bool, SortFileError> {
+    let msg = fs::read(&path)?;
+    sort_protobuf_file_contents_in_place(path, &msg)
+}
+
+/// Same as [`sort_protobuf_file_in_place`], but for the file contents already read into `msg`,
+/// so that the file isn't read again.
+pub fn sort_protobuf_file_contents_in_place(path: impl AsRef<Path>, msg: &[u8]) -> Result<bool, SortFileError> {
+    match sort_protobuf_message(msg)? {
+        Cow::Owned(sorted) => {
+            replace_file(path.as_ref(), &sorted)?;
+            Ok(true)
+        }
+        Cow::Borrowed(_) => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_file, sort_protobuf_file_contents_in_place, sort_protobuf_file_in_place, SortFileError};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("protofixer-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_sort_protobuf_file() {
+        let input = temp_path("input.bin");
+        let output = temp_path("output.bin");
+        fs::write(&input, [0x18, 0x03, 0x08, 0x01]).unwrap();
+        assert!(sort_protobuf_file(&input, &output).unwrap());
+        assert_eq!(fs::read(&output).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        assert_eq!(fs::read(&input).unwrap(), [0x18, 0x03, 0x08, 0x01]);
+        assert!(!sort_protobuf_file(&output, &input).unwrap());
+        assert_eq!(fs::read(&input).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn test_sort_protobuf_file_in_place() {
+        let path = temp_path("in-place.bin");
+        fs::write(&path, [0x18, 0x03, 0x08, 0x01]).unwrap();
+        assert!(sort_protobuf_file_in_place(&path).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        assert!(!sort_protobuf_file_in_place(&path).unwrap());
+        fs::write(&path, [0x0F]).unwrap();
+        assert!(matches!(
+            sort_protobuf_file_in_place(&path),
+            Err(SortFileError::Parse(_))
+        ));
+        assert_eq!(fs::read(&path).unwrap(), [0x0F]);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(sort_protobuf_file_in_place(&path), Err(SortFileError::Io(_))));
+    }
+
+    #[test]
+    fn test_sort_protobuf_file_contents_in_place() {
+        let path = temp_path("contents-in-place.bin");
+        fs::write(&path, [0x0F]).unwrap();
+        // The given contents are sorted, not the ones in the file
+        assert!(sort_protobuf_file_contents_in_place(&path, &[0x18, 0x03, 0x08, 0x01]).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        assert!(!sort_protobuf_file_contents_in_place(&path, &[0x08, 0x01]).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        assert!(matches!(
+            sort_protobuf_file_contents_in_place(&path, &[0x0F]),
+            Err(SortFileError::Parse(_))
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sort_protobuf_file_utf8_path() {
+        let path = camino::Utf8PathBuf::try_from(temp_path("utf8.bin")).unwrap();
+        fs::write(&path, [0x18, 0x03, 0x08, 0x01]).unwrap();
+        assert!(sort_protobuf_file_in_place(&path).unwrap());
+        assert!(!sort_protobuf_file(path.as_path(), path.as_std_path()).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_sort_protobuf_file_atomic() {
+        use super::sort_protobuf_file_atomic;
+
+        let path = temp_path("atomic.bin");
+        fs::write(&path, [0x18, 0x03, 0x08, 0x01]).unwrap();
+        sort_protobuf_file_atomic(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), [0x08, 0x01, 0x18, 0x03]);
+        fs::write(&path, []).unwrap();
+        sort_protobuf_file_atomic(&path).unwrap();
+        assert!(fs::read(&path).unwrap().is_empty());
+        fs::write(&path, [0x18, 0x03, 0x0F]).unwrap();
+        assert!(matches!(sort_protobuf_file_atomic(&path), Err(SortFileError::Parse(_))));
+        assert_eq!(fs::read(&path).unwrap(), [0x18, 0x03, 0x0F]);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(sort_protobuf_file_atomic(&path), Err(SortFileError::Io(_))));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{is_sorted, parse_field, Chunk, ParseError};
+use std::mem::MaybeUninit;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum SortArrayError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("output buffer is too small for protobuf message of {0} bytes")]
+    OutputTooSmall(usize),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// into the given fixed-size buffer, without any heap allocations.
+///
+/// The message must fit into the buffer, i.e. `msg.len() <= N`, otherwise
+/// [`SortArrayError::OutputTooSmall`] is returned. The fields list is kept on the stack
+/// as well, taking about `32 * N` bytes of it on 64-bit targets. Returns the message length.
+pub fn sort_protobuf_message_arrayvec<const N: usize>(msg: &[u8], out: &mut [u8; N]) -> Result<usize, SortArrayError> {
+    if msg.len() > N {
+        return Err(SortArrayError::OutputTooSmall(msg.len()));
+    }
+    // Every field takes at least one byte, so there are at most `N` of them
+    let mut storage = [const { MaybeUninit::<Chunk>::uninit() }; N];
+    let mut count = 0;
+    let mut offset = 0;
+    while offset < msg.len() {
+        let chunk = parse_field(msg, offset)?;
+        offset += chunk.length;
+        storage[count].write(chunk);
+        count += 1;
+    }
+    // SAFETY: the first `count` elements are initialized above, and `MaybeUninit<Chunk>`
+    // has the same layout as `Chunk`
+    let chunks = unsafe { &mut *(&mut storage[..count] as *mut [MaybeUninit<Chunk>] as *mut [Chunk]) };
+    if is_sorted(chunks) {
+        out[..msg.len()].copy_from_slice(msg);
+    } else {
+        // Tie-break by offset to keep repeated fields order, unstable sort doesn't allocate
+        chunks.sort_unstable_by_key(|ck| (ck.id, ck.offset));
+        let mut written = 0;
+        for ck in chunks.iter() {
+            out[written..written + ck.length].copy_from_slice(ck.bytes(msg));
+            written += ck.length;
+        }
+    }
+    Ok(msg.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_arrayvec, SortArrayError};
+
+    #[test]
+    fn test_sort_message_arrayvec() {
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02, 0x08, 0x04];
+        let mut out = [0; 10];
+        assert_eq!(sort_protobuf_message_arrayvec(&msg, &mut out).unwrap(), 8);
+        assert_eq!(out[..8], [0x08, 0x01, 0x08, 0x04, 0x10, 0x02, 0x18, 0x03]);
+        let sorted = out;
+        assert_eq!(sort_protobuf_message_arrayvec(&sorted[..8], &mut out).unwrap(), 8);
+        assert_eq!(out, sorted);
+        assert_eq!(sort_protobuf_message_arrayvec(&[], &mut [0; 0]).unwrap(), 0);
+        assert!(matches!(
+            sort_protobuf_message_arrayvec(&msg, &mut [0; 7]),
+            Err(SortArrayError::OutputTooSmall(8))
+        ));
+        assert!(matches!(
+            sort_protobuf_message_arrayvec(&[0x0F], &mut out),
+            Err(SortArrayError::Parse(_))
+        ));
+    }
+}
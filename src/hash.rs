@@ -0,0 +1,294 @@
+use crate::{is_sorted, parse_message, sort_chunks, sort_protobuf_message, ParseError};
+use std::borrow::Cow;
+#[cfg(feature = "blake3")]
+use std::collections::BTreeMap;
+#[cfg(feature = "blake3")]
+use std::fmt;
+use std::hash::Hasher;
+use thiserror::Error;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+/// Reversed CRC-32 (IEEE 802.3) polynomial
+const CRC32_POLYNOMIAL: u32 = 0xedb8_8320;
+
+#[derive(Debug, Clone, Error)]
+pub enum VerifiedSortError {
+    #[error("protobuf message checksum {actual:#010x} doesn't match the expected {expected:#010x}")]
+    ChecksumMismatch { expected: u64, actual: u64 },
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Compute 64-bit FNV-1a hash of the given protobuf message with fields sorted
+/// in the "canonical" order, without materializing the sorted message.
+///
+/// Messages differing only in the fields order have the same checksum.
+pub fn message_checksum(msg: &[u8]) -> Result<u64, ParseError> {
+    let mut chunks = parse_message(msg)?;
+    if !is_sorted(&chunks) {
+        sort_chunks(&mut chunks);
+    }
+    let mut hash = FNV_OFFSET_BASIS;
+    for ck in &chunks {
+        for &byte in ck.bytes(msg) {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+    Ok(hash)
+}
+
+/// Feed the given protobuf message with fields sorted in the "canonical" order (by field ID)
+/// into the hasher, without materializing the sorted message.
+///
+/// An already sorted message is written in a single call, otherwise each field is written
+/// separately, so the result matches hashing the sorted message only if the hasher doesn't
+/// depend on how the input is split into [`Hasher::write`] calls.
+pub fn hash_protobuf_message_sorted<H: Hasher>(msg: &[u8], hasher: &mut H) -> Result<(), ParseError> {
+    let mut chunks = parse_message(msg)?;
+    if is_sorted(&chunks) {
+        hasher.write(msg);
+        return Ok(());
+    }
+    sort_chunks(&mut chunks);
+    chunks.iter().for_each(|ck| hasher.write(ck.bytes(msg)));
+    Ok(())
+}
+
+/// Verify CRC-32 checksum of the given protobuf message, then sort its fields in the "canonical"
+/// order (by field ID). Returns the sorted message along with its CRC-32 checksum.
+pub fn sort_protobuf_message_verified(
+    msg: &[u8],
+    expected_checksum: u64,
+) -> Result<(Cow<'_, [u8]>, u64), VerifiedSortError> {
+    let actual = u64::from(crc32(msg));
+    if actual != expected_checksum {
+        return Err(VerifiedSortError::ChecksumMismatch {
+            expected: expected_checksum,
+            actual,
+        });
+    }
+    let sorted = sort_protobuf_message(msg)?;
+    let checksum = match sorted {
+        Cow::Borrowed(_) => actual,
+        Cow::Owned(ref sorted) => u64::from(crc32(sorted)),
+    };
+    Ok((sorted, checksum))
+}
+
+/// CRC-32 (IEEE 802.3) of the given bytes, as used by zlib
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (CRC32_POLYNOMIAL & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and compute BLAKE3 hash of the sorted message in the same pass.
+#[cfg(feature = "blake3")]
+pub fn sort_protobuf_message_with_hash(msg: &[u8]) -> Result<(Cow<'_, [u8]>, [u8; 32]), ParseError> {
+    let mut chunks = parse_message(msg)?;
+    if is_sorted(&chunks) {
+        return Ok((Cow::Borrowed(msg), *blake3::hash(msg).as_bytes()));
+    }
+    sort_chunks(&mut chunks);
+    let mut hasher = blake3::Hasher::new();
+    let mut sorted = Vec::with_capacity(msg.len());
+    for ck in &chunks {
+        let bytes = ck.bytes(msg);
+        hasher.update(bytes);
+        sorted.extend_from_slice(bytes);
+    }
+    Ok((Cow::Owned(sorted), *hasher.finalize().as_bytes()))
+}
+
+/// Compute BLAKE3 hash of the given field of a protobuf message, or `None` if it's missing.
+///
+/// The hash covers the payloads of all the field occurrences in the message order, with the length
+/// prefix of length-delimited ones, so that the boundaries between occurrences are unambiguous.
+/// Tags are not hashed, thus overlong tag encodings don't change the hash.
+#[cfg(feature = "blake3")]
+pub fn hash_field(msg: &[u8], field_id: u32) -> Result<Option<[u8; 32]>, ParseError> {
+    let mut hasher: Option<blake3::Hasher> = None;
+    for ck in parse_message(msg)?.iter().filter(|ck| ck.id == field_id) {
+        hasher
+            .get_or_insert_with(blake3::Hasher::new)
+            .update(&ck.bytes(msg)[ck.tag_length(msg)..]);
+    }
+    Ok(hasher.map(|hasher| *hasher.finalize().as_bytes()))
+}
+
+/// Compute [`hash_field`] of each field of the given protobuf message in one pass.
+#[cfg(feature = "blake3")]
+pub fn field_hashes(msg: &[u8]) -> Result<BTreeMap<u32, [u8; 32]>, ParseError> {
+    let mut hashers: BTreeMap<u32, blake3::Hasher> = BTreeMap::new();
+    for ck in &parse_message(msg)? {
+        let hasher = hashers.entry(ck.id).or_default();
+        hasher.update(&ck.bytes(msg)[ck.tag_length(msg)..]);
+    }
+    Ok(hashers
+        .into_iter()
+        .map(|(id, hasher)| (id, *hasher.finalize().as_bytes()))
+        .collect())
+}
+
+/// Content identity of a protobuf message: BLAKE3 hash of the message with fields sorted
+/// in the "canonical" order, so messages differing only in the fields order have the same ID.
+///
+/// `Display` shows it as lowercase hex.
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MessageId([u8; 32]);
+
+#[cfg(feature = "blake3")]
+impl MessageId {
+    /// Sort the given message and hash it.
+    pub fn from_message(msg: &[u8]) -> Result<MessageId, ParseError> {
+        Ok(MessageId(sort_protobuf_message_with_hash(msg)?.1))
+    }
+
+    /// Hash the given message, which must be already sorted, e.g. by [`sort_protobuf_message`].
+    /// Otherwise the ID won't match [`MessageId::from_message`].
+    pub fn from_sorted_message_unchecked(sorted: &[u8]) -> MessageId {
+        MessageId(*blake3::hash(sorted).as_bytes())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        crc32, hash_protobuf_message_sorted, message_checksum, sort_protobuf_message_verified, VerifiedSortError,
+    };
+    #[cfg(feature = "blake3")]
+    use super::{field_hashes, hash_field, sort_protobuf_message_with_hash, MessageId};
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_message_checksum() {
+        assert_eq!(message_checksum(&[]).unwrap(), 0xcbf2_9ce4_8422_2325);
+        let checksum = message_checksum(&[0x08, 0x01, 0x10, 0x02, 0x18, 0x03]).unwrap();
+        assert_eq!(checksum, 0x9def_78ec_7522_bd9d);
+        assert_eq!(
+            message_checksum(&[0x18, 0x03, 0x08, 0x01, 0x10, 0x02]).unwrap(),
+            checksum
+        );
+        assert_ne!(
+            message_checksum(&[0x18, 0x04, 0x08, 0x01, 0x10, 0x02]).unwrap(),
+            checksum
+        );
+        assert!(message_checksum(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_hash_message_sorted() {
+        /// Hasher recording all the writes
+        #[derive(Default)]
+        struct RecordingHasher(Vec<Vec<u8>>);
+
+        impl std::hash::Hasher for RecordingHasher {
+            fn finish(&self) -> u64 {
+                0
+            }
+
+            fn write(&mut self, bytes: &[u8]) {
+                self.0.push(bytes.to_vec());
+            }
+        }
+
+        let mut hasher = RecordingHasher::default();
+        hash_protobuf_message_sorted(&[0x18, 0x03, 0x08, 0x01, 0x08, 0x02], &mut hasher).unwrap();
+        assert_eq!(hasher.0, [[0x08, 0x01], [0x08, 0x02], [0x18, 0x03]]);
+        let mut hasher = RecordingHasher::default();
+        hash_protobuf_message_sorted(&[0x08, 0x01, 0x18, 0x03], &mut hasher).unwrap();
+        assert_eq!(hasher.0, [[0x08, 0x01, 0x18, 0x03]]);
+        let mut hasher = RecordingHasher::default();
+        assert!(hash_protobuf_message_sorted(&[0x18, 0x03, 0x0F], &mut hasher).is_err());
+        assert!(hasher.0.is_empty());
+    }
+
+    #[test]
+    fn test_sort_message_verified() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        let msg = [0x18, 0x03, 0x08, 0x01];
+        let (sorted, checksum) = sort_protobuf_message_verified(&msg, u64::from(crc32(&msg))).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x18, 0x03].as_slice());
+        assert_eq!(checksum, u64::from(crc32(&sorted)));
+        let (sorted_again, checksum_again) = sort_protobuf_message_verified(&sorted, checksum).unwrap();
+        assert!(matches!(sorted_again, Cow::Borrowed(_)));
+        assert_eq!(checksum_again, checksum);
+        assert!(matches!(
+            sort_protobuf_message_verified(&msg, checksum),
+            Err(VerifiedSortError::ChecksumMismatch { expected, .. }) if expected == checksum
+        ));
+        assert!(matches!(
+            sort_protobuf_message_verified(&[0x0F], u64::from(crc32(&[0x0F]))),
+            Err(VerifiedSortError::Parse(_))
+        ));
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_sort_message_with_hash() {
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02];
+        let (sorted, hash) = sort_protobuf_message_with_hash(&msg).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x10, 0x02, 0x18, 0x03].as_slice());
+        assert_eq!(hash, *blake3::hash(&sorted).as_bytes());
+        let (sorted_again, hash_again) = sort_protobuf_message_with_hash(&sorted).unwrap();
+        assert!(matches!(sorted_again, Cow::Borrowed(_)));
+        assert_eq!(hash_again, hash);
+        assert!(sort_protobuf_message_with_hash(&[0x0F]).is_err());
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_message_id() {
+        let id = MessageId::from_message(&[0x18, 0x03, 0x08, 0x01]).unwrap();
+        assert_eq!(id, MessageId::from_message(&[0x08, 0x01, 0x18, 0x03]).unwrap());
+        assert_eq!(id, MessageId::from_sorted_message_unchecked(&[0x08, 0x01, 0x18, 0x03]));
+        assert_ne!(id, MessageId::from_message(&[0x08, 0x02, 0x18, 0x03]).unwrap());
+        assert_eq!(id.as_bytes(), blake3::hash(&[0x08, 0x01, 0x18, 0x03]).as_bytes());
+        assert_eq!(
+            MessageId::from_message(&[]).unwrap().to_string(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert!(MessageId::from_message(&[0x0F]).is_err());
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_field_hashes() {
+        // Fields 2 ("ab", "c"), 1 with overlong tag, 2
+        let msg = [0x12, 0x02, b'a', b'b', 0x88, 0x00, 0x05, 0x12, 0x01, b'c'];
+        let hash = hash_field(&msg, 2).unwrap().unwrap();
+        assert_eq!(hash, *blake3::hash(&[0x02, b'a', b'b', 0x01, b'c']).as_bytes());
+        // Different split of the same payload bytes
+        let other = [0x12, 0x01, b'a', 0x12, 0x02, b'b', b'c'];
+        assert_ne!(hash_field(&other, 2).unwrap().unwrap(), hash);
+        assert_eq!(hash_field(&msg, 1).unwrap().unwrap(), *blake3::hash(&[0x05]).as_bytes());
+        assert_eq!(hash_field(&msg, 1).unwrap(), hash_field(&[0x08, 0x05], 1).unwrap());
+        assert_eq!(hash_field(&msg, 3).unwrap(), None);
+        assert!(hash_field(&[0x0F], 1).is_err());
+        let hashes = field_hashes(&msg).unwrap();
+        assert_eq!(hashes.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(hashes[&2], hash);
+        assert_eq!(Some(hashes[&1]), hash_field(&msg, 1).unwrap());
+        assert!(field_hashes(&[]).unwrap().is_empty());
+        assert!(field_hashes(&[0x0F]).is_err());
+    }
+}
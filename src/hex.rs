@@ -0,0 +1,45 @@
+use std::fmt::Write;
+
+/// Lowercase hex digits of the given bytes
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 * bytes.len());
+    for byte in bytes {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// Decode bytes from hex digits, `None` if `hex` is not an even number of hex digits
+#[cfg(any(feature = "serde-json", feature = "text-proto"))]
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 == 1 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_hex;
+
+    #[test]
+    fn test_encode_hex() {
+        assert_eq!(encode_hex(&[]), "");
+        assert_eq!(encode_hex(&[0x00, 0x4e, 0xff]), "004eff");
+    }
+
+    #[cfg(any(feature = "serde-json", feature = "text-proto"))]
+    #[test]
+    fn test_decode_hex() {
+        use super::decode_hex;
+
+        assert_eq!(decode_hex("004eFF"), Some(vec![0x00, 0x4e, 0xff]));
+        assert_eq!(decode_hex(""), Some(vec![]));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("zz"), None);
+        assert_eq!(decode_hex("é0"), None);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{parse_message, ParseError};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Longest bar of [`FieldHistogram`] display, longer ones are scaled down
+const MAX_BAR_WIDTH: usize = 50;
+
+/// Number of occurrences of each field ID in a protobuf message, see [`message_field_histogram`].
+///
+/// `Display` shows an ASCII bar chart, one field ID per line in ascending order, e.g. `3 | ## 2`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldHistogram {
+    counts: HashMap<u32, usize>,
+}
+
+impl FieldHistogram {
+    pub fn counts(&self) -> &HashMap<u32, usize> {
+        &self.counts
+    }
+
+    pub fn into_counts(self) -> HashMap<u32, usize> {
+        self.counts
+    }
+}
+
+impl fmt::Display for FieldHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut counts: Vec<(u32, usize)> = self.counts.iter().map(|(&id, &count)| (id, count)).collect();
+        counts.sort_unstable();
+        let id_width = counts.iter().map(|(id, _)| id.to_string().len()).max().unwrap_or(0);
+        let max_count = counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+        for (id, count) in counts {
+            // Round up, so that every field gets a visible bar
+            let width = if max_count <= MAX_BAR_WIDTH {
+                count
+            } else {
+                (count * MAX_BAR_WIDTH).div_ceil(max_count)
+            };
+            writeln!(f, "{:>id_width$} | {} {}", id, "#".repeat(width), count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Count occurrences of each field ID in the given protobuf message.
+pub fn message_field_histogram(msg: &[u8]) -> Result<FieldHistogram, ParseError> {
+    let mut counts = HashMap::new();
+    for ck in &parse_message(msg)? {
+        *counts.entry(ck.id).or_default() += 1;
+    }
+    Ok(FieldHistogram { counts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::message_field_histogram;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_message_field_histogram() {
+        // Fields 12, 1, 12, 3, 12
+        let msg = [0x60, 0x01, 0x08, 0x01, 0x60, 0x02, 0x18, 0x03, 0x60, 0x03];
+        let histogram = message_field_histogram(&msg).unwrap();
+        assert_eq!(histogram.counts(), &HashMap::from([(1, 1), (3, 1), (12, 3)]));
+        assert_eq!(histogram.to_string(), " 1 | # 1\n 3 | # 1\n12 | ### 3\n");
+        assert_eq!(message_field_histogram(&[]).unwrap().to_string(), "");
+        assert!(message_field_histogram(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_field_histogram_scaled_bars() {
+        let mut msg = vec![0x10, 0x00];
+        for _ in 0..100 {
+            msg.extend_from_slice(&[0x08, 0x00]);
+        }
+        let histogram = message_field_histogram(&msg).unwrap();
+        assert_eq!(histogram.to_string(), format!("1 | {} 100\n2 | # 1\n", "#".repeat(50)));
+        assert_eq!(histogram.into_counts()[&1], 100);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::hex::{decode_hex, encode_hex};
+use crate::{parse_fields, EncodeError, MessageBuilder, ParseError, WireType};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum JsonParseError {
+    #[error("JSON field #{0} is not a valid {{field_id, wire_type, payload_hex}} object")]
+    InvalidField(usize),
+    #[error("JSON fields must be an array")]
+    NotArray,
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// Describe fields of the given protobuf message as a JSON array, e.g.:
+/// `[{"field_id": 1, "wire_type": "Varint", "payload_hex": "54"}]`.
+///
+/// Payloads don't include the tag and the length prefix.
+pub fn message_to_json_fields(msg: &[u8]) -> Result<Value, ParseError> {
+    let fields = parse_fields(msg)?.into_iter().map(|field| {
+        json!({
+            "field_id": field.id(),
+            "wire_type": format!("{:?}", field.wire_type()),
+            "payload_hex": encode_hex(field.payload()),
+        })
+    });
+    Ok(Value::Array(fields.collect()))
+}
+
+/// Encode a protobuf message from its [`message_to_json_fields`] description,
+/// with fields sorted in the "canonical" order (by field ID).
+pub fn sort_proto_from_json_fields(json: &Value) -> Result<Vec<u8>, JsonParseError> {
+    let mut builder = MessageBuilder::new();
+    for (i, field) in json.as_array().ok_or(JsonParseError::NotArray)?.iter().enumerate() {
+        let (field_id, wire_type, payload) = parse_json_field(field).ok_or(JsonParseError::InvalidField(i))?;
+        builder.push_field(field_id, wire_type, &payload);
+    }
+    Ok(builder.build()?)
+}
+
+fn parse_json_field(field: &Value) -> Option<(u32, WireType, Vec<u8>)> {
+    let field_id = u32::try_from(field.get("field_id")?.as_u64()?).ok()?;
+    let wire_type = match field.get("wire_type")?.as_str()? {
+        "Varint" => WireType::Varint,
+        "Fixed64" => WireType::Fixed64,
+        "LengthDelimited" => WireType::LengthDelimited,
+        "Fixed32" => WireType::Fixed32,
+        _ => return None,
+    };
+    let payload = decode_hex(field.get("payload_hex")?.as_str()?)?;
+    Some((field_id, wire_type, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{message_to_json_fields, sort_proto_from_json_fields, JsonParseError};
+    use serde_json::json;
+
+    #[test]
+    fn test_message_to_json_fields() {
+        // Fields 3, 1
+        let msg = [0x1a, 0x02, b'h', b'i', 0x08, 0x54];
+        let fields = message_to_json_fields(&msg).unwrap();
+        assert_eq!(
+            fields,
+            json!([
+                {"field_id": 3, "wire_type": "LengthDelimited", "payload_hex": "6869"},
+                {"field_id": 1, "wire_type": "Varint", "payload_hex": "54"},
+            ])
+        );
+        assert_eq!(
+            sort_proto_from_json_fields(&fields).unwrap(),
+            [0x08, 0x54, 0x1a, 0x02, b'h', b'i']
+        );
+        assert!(message_to_json_fields(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_proto_from_json_fields_errors() {
+        assert!(matches!(
+            sort_proto_from_json_fields(&json!({})),
+            Err(JsonParseError::NotArray)
+        ));
+        for field in [
+            json!({"field_id": 1, "wire_type": "Group", "payload_hex": "01"}),
+            json!({"field_id": 1, "wire_type": "Varint", "payload_hex": "0"}),
+            json!({"field_id": 1, "wire_type": "Varint", "payload_hex": "zz"}),
+            json!({"wire_type": "Varint", "payload_hex": "01"}),
+        ] {
+            assert!(matches!(
+                sort_proto_from_json_fields(&json!([field])),
+                Err(JsonParseError::InvalidField(0))
+            ));
+        }
+        let field = json!({"field_id": 1, "wire_type": "Fixed32", "payload_hex": "01"});
+        assert!(matches!(
+            sort_proto_from_json_fields(&json!([field])),
+            Err(JsonParseError::Encode(_))
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{do_sort, parse_fields, Chunk, Field, ParseError};
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+/// Protobuf message parsed on first use, see [`lazy_message`].
+///
+/// The parse result, including the failure, is cached, so queries don't parse the message again.
+/// It's kept in a [`OnceLock`] rather than a `OnceCell`, so the message stays `Sync`.
+#[derive(Debug, Clone)]
+pub struct LazyMessage<'a> {
+    raw: &'a [u8],
+    fields: OnceLock<Result<Vec<Field<'a>>, ParseError>>,
+}
+
+/// Wrap the given protobuf message without parsing it yet.
+pub fn lazy_message(msg: &[u8]) -> LazyMessage<'_> {
+    LazyMessage {
+        raw: msg,
+        fields: OnceLock::new(),
+    }
+}
+
+impl<'a> LazyMessage<'a> {
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// Fields of the message in the message order, parsing it on the first call.
+    pub fn fields(&self) -> Result<&[Field<'a>], ParseError> {
+        match self.fields.get_or_init(|| parse_fields(self.raw)) {
+            Ok(fields) => Ok(fields),
+            Err(err) => Err(err.clone()),
+        }
+    }
+
+    /// Check if the fields are sorted by field ID.
+    pub fn is_sorted(&self) -> Result<bool, ParseError> {
+        Ok(self.fields()?.windows(2).all(|w| w[0].id() <= w[1].id()))
+    }
+
+    /// The message with fields sorted in the "canonical" order (by field ID), borrowed if
+    /// it's already sorted.
+    pub fn sorted_bytes(&self) -> Result<Cow<'a, [u8]>, ParseError> {
+        if self.is_sorted()? {
+            return Ok(Cow::Borrowed(self.raw));
+        }
+        let mut chunks: Vec<Chunk> = self.fields()?.iter().map(|field| field.to_chunk()).collect();
+        Ok(Cow::Owned(do_sort(&mut chunks, self.raw)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lazy_message;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_lazy_message() {
+        // Fields 2, 1, 2
+        let msg = [0x10, 0x02, 0x08, 0x01, 0x10, 0x03];
+        let lazy = lazy_message(&msg);
+        assert!(lazy.fields.get().is_none());
+        assert!(!lazy.is_sorted().unwrap());
+        assert!(lazy.fields.get().is_some());
+        assert_eq!(lazy.fields().unwrap().len(), 3);
+        assert_eq!(
+            lazy.sorted_bytes().unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x10, 0x03].as_slice()
+        );
+        let lazy = lazy_message(&msg[2..]);
+        assert!(matches!(lazy.sorted_bytes().unwrap(), Cow::Borrowed(_)));
+        assert_eq!(lazy.raw(), &msg[2..]);
+        let lazy = lazy_message(&[0x0F]);
+        assert!(lazy.is_sorted().is_err());
+        assert!(lazy.sorted_bytes().is_err());
+    }
+}
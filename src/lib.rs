@@ -1,24 +1,322 @@
 //! A small library that takes a protobuf serialized message and sorts fields inside,
 //! so that the field order becomes deterministic.
+//!
+//! All the public types are `Send + Sync`, so they can be shared across threads and async tasks.
+
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 
 use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+mod access;
+#[cfg(feature = "allocator-api")]
+mod allocator;
+#[cfg(feature = "tokio")]
+mod async_write;
+#[cfg(feature = "base64")]
+mod base64_codec;
+mod buffered;
+mod builder;
+#[cfg(feature = "lru-cache")]
+mod cache;
+#[cfg(feature = "cancellation")]
+mod cancel;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod chunked;
+#[cfg(feature = "tokio-codec")]
+mod codec;
+#[cfg(any(feature = "zstd", feature = "lz4", feature = "snappy"))]
+mod compress;
+mod const_sort;
+mod crypto;
+#[cfg(feature = "debug-fmt")]
+mod debug_fmt;
+mod decoder;
+mod delimited;
+#[cfg(feature = "serde")]
+mod deser;
+mod diff;
+mod encode;
+mod field;
+mod field_set;
+mod file;
+mod fixed;
+mod hash;
+mod hex;
+mod histogram;
+#[cfg(feature = "serde-json")]
+mod json;
+mod lazy;
+mod map;
+mod ndjson;
+mod options;
+mod ordering;
+mod packed;
+#[cfg(feature = "padding")]
+mod padding;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+mod processor;
+mod progress;
+mod proto3;
+mod rebuild;
+mod report;
+mod rewrite;
+mod ring;
+mod schema;
+mod signing;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+#[cfg(feature = "proptest-strategies")]
+pub mod strategies;
+#[cfg(feature = "futures-integration")]
+mod stream;
+#[cfg(feature = "testing-modes")]
+mod testing;
+#[cfg(feature = "text-proto")]
+mod text_proto;
+mod transform;
+mod validate;
+mod validator;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use access::{build_index, collect_field_payloads, get_nested_field_bytes, MessageIndex};
+#[cfg(feature = "allocator-api")]
+pub use allocator::sort_protobuf_message_in;
+#[cfg(feature = "tokio")]
+pub use async_write::{sort_protobuf_message_async_write, AsyncSortError};
+#[cfg(feature = "base64")]
+pub use base64_codec::{sort_protobuf_message_from_base64, sort_protobuf_message_to_base64, DecodeOrParseError};
+pub use buffered::{sort_protobuf_message_buffered, SortReadError};
+pub use builder::{MessageBuilder, MessageSizeEstimator, SortedMessageBytes};
+#[cfg(feature = "lru-cache")]
+pub use cache::SortingCache;
+#[cfg(feature = "cancellation")]
+pub use cancel::{sort_protobuf_message_cancellable, CancellableSortError, CancellationToken};
+#[cfg(feature = "cbor")]
+pub use cbor::{sort_and_convert_to_cbor, CborConvertError};
+pub use chunked::{
+    concat_and_sort_field_bytes, concat_field_bytes, sort_protobuf_message_chunked, sort_protobuf_message_to_iov,
+    sorted_field_chunks, ChunkedSortedMessage,
+};
+#[cfg(feature = "tokio-codec")]
+pub use codec::{ProtoSortCodec, ProtoSortCodecError, DEFAULT_MAX_FRAME_LENGTH};
+#[cfg(any(feature = "zstd", feature = "lz4", feature = "snappy"))]
+pub use compress::{
+    decompress_and_sort_protobuf_message, sort_and_compress_protobuf_message, CompressError, CompressionCodec,
+    DecompressOrParseError,
+};
+pub use const_sort::sort_protobuf_message_const;
+pub use crypto::{sort_protobuf_message_with_encryption, CryptoSortError, DecryptError};
+#[cfg(feature = "debug-fmt")]
+pub use debug_fmt::message_to_debug_string;
+pub use decoder::{decode_length_delimited_payload, FieldDecoder};
+pub use delimited::sort_delimited_message_stream;
+#[cfg(feature = "serde")]
+pub use deser::{sorted_message_from_serde, SortDeserError};
+pub use diff::{diff_protobuf_messages, FieldChange, MessageDiff};
+pub use encode::{encode_length_delimited_field, EncodeError};
+pub use field::{debug_field, parse_fields, parse_message_zero_copy, Field, FieldDebug, WireType};
+pub use field_set::MessageFieldSet;
+pub use file::{sort_protobuf_file, sort_protobuf_file_contents_in_place, sort_protobuf_file_in_place, SortFileError};
+#[cfg(feature = "mmap")]
+pub use file::{sort_protobuf_file_atomic, AtomicSortError};
+pub use fixed::{sort_protobuf_message_arrayvec, SortArrayError};
+#[cfg(feature = "blake3")]
+pub use hash::{field_hashes, hash_field, sort_protobuf_message_with_hash, MessageId};
+pub use hash::{hash_protobuf_message_sorted, message_checksum, sort_protobuf_message_verified, VerifiedSortError};
+pub use histogram::{message_field_histogram, FieldHistogram};
+#[cfg(feature = "serde-json")]
+pub use json::{message_to_json_fields, sort_proto_from_json_fields, JsonParseError};
+pub use lazy::{lazy_message, LazyMessage};
+pub use map::sort_proto_map_entries;
+pub use ndjson::{stream_message_as_ndjson, NdjsonError};
+pub use options::{
+    has_reserved_field_numbers, is_extension_field, reserved_field_numbers, sort_protobuf_message_with_options,
+    CustomWireTypeHandler, SortError, SortOptions, DEFAULT_MAX_FIELDS,
+};
+pub use ordering::{
+    is_sorted_by_size, is_sorted_by_wire_then_id, sort_message_fields_by_name, sort_protobuf_message_by_size,
+    sort_protobuf_message_by_wire_then_id, sort_protobuf_message_defaults_last, sort_protobuf_message_excluding,
+    sort_protobuf_message_with_oneofs, sort_protobuf_message_with_tie_breaker, swap_fields, SwapError,
+};
+pub use packed::sort_packed_varint_field;
+#[cfg(feature = "padding")]
+pub use padding::{sort_protobuf_message_padded, strip_padding};
+#[cfg(feature = "rayon")]
+pub use parallel::sort_multiple_parallel;
+pub use patch::{apply_patch, MessagePatch, PatchError};
+pub use processor::MessageProcessor;
+pub use progress::{
+    sort_protobuf_message_instrumented, sort_protobuf_message_with_progress, SortMetrics, SortPhase, SortProgress,
+};
+pub use proto3::{is_proto3_compliant, Cardinality, Proto3ComplianceError, Proto3Schema, Proto3Violation, ValueKind};
+pub use rebuild::MessageRebuilder;
+pub use report::{
+    sort_message_report_changes, sort_protobuf_message_verbose, FieldMove, SortLog, SortLogEntry, SortReport,
+};
+pub use rewrite::{MessageRewriter, RewriteError};
+pub use ring::sort_protobuf_message_ring;
+pub use schema::{sort_and_validate_message, MessageSchema, SchemaError, SortValidateError};
+pub use signing::{prepare_message_for_signing, PrepareError};
+#[cfg(feature = "futures-integration")]
+pub use stream::{sort_protobuf_message_stream, SortStreamError};
+#[cfg(feature = "text-proto")]
+pub use text_proto::{message_to_text_proto, text_proto_to_message};
+pub use transform::{apply_field_transform, TransformError};
+pub use validate::{
+    check_message_size_limit, sort_protobuf_message_limited_size, sort_protobuf_message_validated,
+    verify_sort_parse_equivalence, EquivalenceError, FieldTooLargeError, FieldValidationError, SizeLimitError,
+    SortSizeError,
+};
+pub use validator::{
+    ComposedValidator, FieldCountValidator, FieldSizeValidator, MessageValidator, MinimalVarintValidator,
+    NoZeroFieldIdValidator, SortedValidator, ValidationError,
+};
+#[cfg(feature = "wasm")]
+pub use wasm::{is_proto_message_sorted_wasm, sort_proto_message_wasm};
+
+/// Malformed protobuf message. With the `miette` feature, it's also a `miette::Diagnostic`.
+#[derive(Debug, Clone, Error)]
+#[cfg_attr(feature = "miette", derive(miette::Diagnostic))]
+#[cfg_attr(
+    feature = "miette",
+    diagnostic(
+        code(protofixer::parse::failed),
+        help("ensure the input is a valid proto3 wire-format encoded message")
+    )
+)]
 #[error("failed to parse protobuf message")]
 pub struct ParseError;
 
+/// [`ParseError`] with caller-provided context, e.g. the name of the failed message.
+#[derive(Debug, Clone, Error)]
+#[error("failed to parse protobuf message (context: {context}): {inner}")]
+pub struct ContextualParseError<C: fmt::Display> {
+    pub context: C,
+    #[source]
+    pub inner: ParseError,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum SortPanicError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("sorting protobuf message panicked: {0}")]
+    Panicked(String),
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum SortTimeoutError {
+    #[error("sorting protobuf message timed out")]
+    TimedOut,
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Notice that only a part of the message was sorted, see [`sort_protobuf_message_with_retry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortWarning {
+    /// Offset of the first unparseable byte, the rest of the message is left as is.
+    pub unsorted_offset: usize,
+}
+
+/// Maximum field ID allowed by protobuf spec (29 bits)
+const MAX_FIELD_ID: u64 = (1 << 29) - 1;
+/// Maximum length of a varint, enough to encode any 64-bit value
+const MAX_VARINT_LENGTH: usize = 10;
+
 /// Checks if the given serialized protobuf message has "canonical" fields order,
 /// i.e. ordered by field ID.
+///
+/// Parsing stops at the first out-of-order field, so the rest of an unsorted message
+/// may be malformed.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(msg_len_bytes = msg.len(), was_sorted = tracing::field::Empty)
+    )
+)]
 pub fn is_protobuf_message_sorted(msg: &[u8]) -> Result<bool, ParseError> {
-    let chunks = parse_message(msg)?;
-    Ok(is_sorted(&chunks))
+    let mut prev = 0;
+    let mut sorted = true;
+    for chunk in PullParser::new(msg) {
+        let chunk = chunk?;
+        if chunk.id < prev {
+            // The rest of the message doesn't matter, and isn't validated
+            sorted = false;
+            break;
+        }
+        prev = chunk.id;
+    }
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("was_sorted", sorted);
+    Ok(sorted)
+}
+
+/// Same as [`is_protobuf_message_sorted`], but treats unparseable message as not sorted.
+pub fn is_protobuf_message_sorted_unchecked(msg: &[u8]) -> bool {
+    is_protobuf_message_sorted(msg).unwrap_or(false)
+}
+
+/// Checks if the given protobuf message has no fields, i.e. is empty. Empty message is valid
+/// and always sorted, so callers may skip sorting it.
+pub fn is_empty_protobuf_message(msg: &[u8]) -> bool {
+    msg.is_empty()
+}
+
+/// Checks if the given protobuf message has exactly one field.
+///
+/// Only the first field is parsed, so the rest of a message with more fields may be malformed.
+pub fn is_single_field_protobuf_message(msg: &[u8]) -> Result<bool, ParseError> {
+    match PullParser::new(msg).next_chunk() {
+        Some(chunk) => Ok(chunk?.length == msg.len()),
+        None => Ok(false),
+    }
+}
+
+/// Panics if the given protobuf message is not parseable or has non-canonical fields order.
+#[track_caller]
+pub fn assert_protobuf_message_sorted(msg: &[u8]) {
+    match is_protobuf_message_sorted(msg) {
+        Ok(true) => {}
+        Ok(false) => panic!("protobuf message fields are not in canonical order"),
+        Err(err) => panic!("protobuf message is not sorted: {}", err),
+    }
 }
 
 /// Sort fields in the given protobuf message in the "canonical" order (by field ID).
-pub fn sort_protobuf_message(msg: &[u8]) -> Result<Cow<[u8]>, ParseError> {
+///
+/// Repeated fields (same field ID) maintain their original relative order.
+/// An already sorted message is returned as `Cow::Borrowed` without copying, otherwise the result
+/// is `Cow::Owned`. An empty message is returned in constant time, without allocations.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(msg_len_bytes = msg.len(), was_sorted = tracing::field::Empty)
+    )
+)]
+pub fn sort_protobuf_message(msg: &[u8]) -> Result<Cow<'_, [u8]>, ParseError> {
     let mut chunks = parse_message(msg)?;
-    if is_sorted(&chunks) {
+    #[cfg(feature = "metrics")]
+    record_sort_metrics(msg.len(), chunks.len());
+    #[cfg(feature = "testing-modes")]
+    if let Some(reordered) = testing::sort_in_testing_order(&mut chunks, msg) {
+        return Ok(Cow::Owned(reordered));
+    }
+    let sorted = is_sorted(&chunks);
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("was_sorted", sorted);
+    if sorted {
         Ok(Cow::Borrowed(msg))
     } else {
         let sorted = do_sort(&mut chunks, msg);
@@ -27,29 +325,371 @@ pub fn sort_protobuf_message(msg: &[u8]) -> Result<Cow<[u8]>, ParseError> {
 }
 
 /// Sort fields in the given protobuf message in the "canonical" order (by field ID).
-pub fn sort_protobuf_message_inplace(msg: &mut [u8]) -> Result<(), ParseError> {
+/// Repeated fields (same field ID) maintain their original relative order.
+///
+/// Returns `true` if the fields had to be reordered, `false` if the message was already sorted.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(msg_len_bytes = msg.len(), was_sorted = tracing::field::Empty)
+    )
+)]
+pub fn sort_protobuf_message_inplace(msg: &mut [u8]) -> Result<bool, ParseError> {
     let mut chunks = parse_message(msg)?;
-    if !is_sorted(&chunks) {
-        let sorted = do_sort(&mut chunks, msg);
-        msg.copy_from_slice(&sorted);
+    #[cfg(feature = "metrics")]
+    record_sort_metrics(msg.len(), chunks.len());
+    let sorted = is_sorted(&chunks);
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("was_sorted", sorted);
+    if sorted {
+        return Ok(false);
+    }
+    let sorted = do_sort(&mut chunks, msg);
+    msg.copy_from_slice(&sorted);
+    Ok(true)
+}
+
+/// Same as [`sort_protobuf_message_inplace`], but returns `true` if the message was already
+/// sorted and left untouched, `false` if the fields had to be reordered.
+pub fn check_or_sort_inplace(msg: &mut [u8]) -> Result<bool, ParseError> {
+    Ok(!sort_protobuf_message_inplace(msg)?)
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and prepend the given prefix, e.g. a content type or version marker, to the result.
+pub fn sort_protobuf_message_with_prefix(msg: &[u8], prefix: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let sorted = sort_protobuf_message(msg)?;
+    let mut result = Vec::with_capacity(prefix.len() + sorted.len());
+    result.extend_from_slice(prefix);
+    result.extend_from_slice(&sorted);
+    Ok(result)
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and put a varint field `timestamp_field_id` with the given timestamp at its sorted position.
+///
+/// Existing occurrences of the timestamp field are replaced. Fails if the field ID is invalid.
+pub fn sort_protobuf_message_with_timestamp(
+    msg: &[u8],
+    timestamp_field_id: u32,
+    timestamp_ns: u64,
+) -> Result<Vec<u8>, ParseError> {
+    let mut timestamp = Vec::with_capacity(15);
+    encode::encode_tag(timestamp_field_id, 0, &mut timestamp).map_err(|_| ParseError)?;
+    write_varint(u128::from(timestamp_ns), &mut timestamp);
+    let mut chunks = parse_message(msg)?;
+    chunks.retain(|ck| ck.id != timestamp_field_id);
+    let mut result = do_sort(&mut chunks, msg);
+    // Insert the timestamp right after the sorted fields with smaller IDs
+    let position = chunks.partition_point(|ck| ck.id < timestamp_field_id);
+    let offset: usize = chunks[..position].iter().map(|ck| ck.length).sum();
+    result.splice(offset..offset, timestamp);
+    Ok(result)
+}
+
+/// Keep only the fields of the given protobuf message listed in `field_ids`, like
+/// `google.protobuf.FieldMask` does, and sort them in the "canonical" order (by field ID).
+pub fn sort_protobuf_message_with_field_mask(msg: &[u8], field_ids: &[u32]) -> Result<Vec<u8>, ParseError> {
+    let mut chunks = parse_message(msg)?;
+    chunks.retain(|ck| field_ids.contains(&ck.id));
+    Ok(do_sort(&mut chunks, msg))
+}
+
+/// Collect a protobuf message of at most `max_size` bytes from the given iterator
+/// and sort its fields in the "canonical" order (by field ID).
+///
+/// Longer messages are rejected as malformed.
+pub fn sort_protobuf_message_from_iter<I: Iterator<Item = u8>>(
+    iter: I,
+    max_size: usize,
+) -> Result<Vec<u8>, ParseError> {
+    let mut msg = Vec::with_capacity(iter.size_hint().0.min(max_size));
+    for byte in iter {
+        if msg.len() == max_size {
+            // Too big message
+            return Err(ParseError);
+        }
+        msg.push(byte);
+    }
+    into_canonical_bytes(msg)
+}
+
+/// Sort fields in the given owned protobuf message in the "canonical" order (by field ID),
+/// reusing its buffer for the result. Already sorted message is returned as is.
+pub fn into_canonical_bytes(msg: impl Into<Vec<u8>>) -> Result<Vec<u8>, ParseError> {
+    let mut msg = msg.into();
+    sort_protobuf_message_inplace(&mut msg)?;
+    Ok(msg)
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// into the given buffer, so that its allocation can be reused across many messages.
+///
+/// The buffer is cleared first. Returns `true` if the fields had to be reordered.
+pub fn sort_protobuf_message_reusing_buffer(msg: &[u8], buf: &mut Vec<u8>) -> Result<bool, ParseError> {
+    buf.clear();
+    let mut chunks = parse_message(msg)?;
+    if is_sorted(&chunks) {
+        buf.extend_from_slice(msg);
+        return Ok(false);
+    }
+    sort_chunks(&mut chunks);
+    buf.reserve(msg.len());
+    for ck in &chunks {
+        buf.extend_from_slice(ck.bytes(msg));
+    }
+    Ok(true)
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// preserving the relative order of repeated field values (fields with the same ID).
+///
+/// Protobuf decoders rely on this order, so it is guaranteed to be kept intact.
+/// [`sort_protobuf_message`] already sorts stably, this is an explicit alias for it.
+pub fn sort_protobuf_message_repeated_stable(msg: &[u8]) -> Result<Cow<'_, [u8]>, ParseError> {
+    sort_protobuf_message(msg)
+}
+
+/// Sort as many complete leading fields of the given protobuf message as possible.
+///
+/// Parsing stops at the first field that can't be parsed, e.g. at garbage bytes
+/// following the message. Returns sorted leading fields and the number of bytes
+/// they occupy in `msg`, the rest `msg[consumed..]` is left for the caller.
+/// If not a single field can be parsed, the whole `msg` is the trailer: `(empty, 0)` is returned.
+pub fn sort_protobuf_message_with_trailer(msg: &[u8]) -> (Cow<'_, [u8]>, usize) {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while offset < msg.len() {
+        match parse_field(msg, offset) {
+            Ok(chunk) => {
+                offset += chunk.length;
+                chunks.push(chunk);
+            }
+            Err(_) => break,
+        }
+    }
+    let head = &msg[..offset];
+    if is_sorted(&chunks) {
+        (Cow::Borrowed(head), offset)
+    } else {
+        (Cow::Owned(do_sort(&mut chunks, msg)), offset)
+    }
+}
+
+/// Sort fields in the given protobuf message, falling back to sorting only the longest
+/// parseable prefix if the whole message can't be parsed.
+///
+/// The unparseable rest of the message is appended verbatim, in which case a [`SortWarning`]
+/// is returned alongside the result. Fails only if not a single field can be parsed.
+pub fn sort_protobuf_message_with_retry(msg: &[u8]) -> Result<(Cow<'_, [u8]>, Option<SortWarning>), ParseError> {
+    let err = match sort_protobuf_message(msg) {
+        Ok(sorted) => return Ok((sorted, None)),
+        Err(err) => err,
+    };
+    let (head, consumed) = sort_protobuf_message_with_trailer(msg);
+    if consumed == 0 {
+        // Not a single field can be parsed
+        return Err(err);
+    }
+    let warning = SortWarning {
+        unsorted_offset: consumed,
+    };
+    match head {
+        Cow::Borrowed(_) => Ok((Cow::Borrowed(msg), Some(warning))),
+        Cow::Owned(mut sorted) => {
+            sorted.extend_from_slice(&msg[consumed..]);
+            Ok((Cow::Owned(sorted), Some(warning)))
+        }
+    }
+}
+
+/// Sort fields in the given protobuf message, skipping the fields that can't be parsed.
+///
+/// On a parse failure the parsing is resumed from the next byte, until a field can be
+/// parsed again. Returns the sorted successfully parsed fields (only) and the errors,
+/// one per each skipped region of the message.
+pub fn sort_protobuf_message_lenient(msg: &[u8]) -> (Cow<'_, [u8]>, Vec<ParseError>) {
+    let mut chunks = Vec::new();
+    let mut errors = Vec::new();
+    let mut offset = 0;
+    let mut skipping = false;
+    while offset < msg.len() {
+        match parse_field(msg, offset) {
+            Ok(chunk) => {
+                offset += chunk.length;
+                chunks.push(chunk);
+                skipping = false;
+            }
+            Err(err) => {
+                if !skipping {
+                    errors.push(err);
+                    skipping = true;
+                }
+                offset += 1;
+            }
+        }
+    }
+    if errors.is_empty() && is_sorted(&chunks) {
+        (Cow::Borrowed(msg), errors)
+    } else {
+        (Cow::Owned(do_sort(&mut chunks, msg)), errors)
+    }
+}
+
+/// Same as [`sort_protobuf_message`], but reuses a thread-local fields list instead of
+/// allocating a new one on every call.
+#[cfg(feature = "thread-local-opt")]
+pub fn sort_protobuf_message_with_thread_local_buffer(msg: &[u8]) -> Result<Cow<'_, [u8]>, ParseError> {
+    use std::cell::RefCell;
+
+    thread_local! {
+        static BUFFER: RefCell<Vec<Chunk>> = const { RefCell::new(Vec::new()) };
     }
-    Ok(())
+
+    BUFFER.with(|buffer| {
+        let mut chunks = buffer.borrow_mut();
+        chunks.clear();
+        for chunk in PullParser::new(msg) {
+            chunks.push(chunk?);
+        }
+        if is_sorted(&chunks) {
+            Ok(Cow::Borrowed(msg))
+        } else {
+            Ok(Cow::Owned(do_sort(&mut chunks, msg)))
+        }
+    })
+}
+
+/// Same as [`sort_protobuf_message`], but attaches the given context to the error.
+pub fn sort_protobuf_message_with_context<C: fmt::Display>(
+    msg: &[u8],
+    context: C,
+) -> Result<Cow<'_, [u8]>, ContextualParseError<C>> {
+    sort_protobuf_message(msg).map_err(|inner| ContextualParseError { context, inner })
+}
+
+/// Sort fields in the given shared protobuf message in the "canonical" order (by field ID).
+///
+/// Already sorted message is returned as is, without copying the bytes.
+pub fn sort_protobuf_arc(msg: Arc<[u8]>) -> Result<Arc<[u8]>, ParseError> {
+    match sort_protobuf_message(&msg)? {
+        Cow::Borrowed(_) => Ok(msg),
+        Cow::Owned(sorted) => Ok(Arc::from(sorted)),
+    }
+}
+
+/// Same as [`sort_protobuf_message`], but the error is shared, e.g. to be reported
+/// by many tasks processing the same message.
+pub fn sort_protobuf_message_shared_err(msg: &[u8]) -> Result<Cow<'_, [u8]>, Arc<ParseError>> {
+    sort_protobuf_message(msg).map_err(Arc::new)
+}
+
+/// Same as [`sort_protobuf_message`], but a panic while sorting, e.g. because of a parser bug,
+/// is caught and reported as an error.
+pub fn sort_protobuf_message_panic_safe(msg: &[u8]) -> Result<Cow<'_, [u8]>, SortPanicError> {
+    match std::panic::catch_unwind(|| sort_protobuf_message(msg)) {
+        Ok(result) => Ok(result?),
+        Err(payload) => Err(SortPanicError::Panicked(panic_message(payload.as_ref()))),
+    }
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// unless the deadline passes. It's checked before parsing and before reassembling the message only.
+pub fn sort_protobuf_message_with_timeout(
+    msg: &[u8],
+    deadline: std::time::Instant,
+) -> Result<Cow<'_, [u8]>, SortTimeoutError> {
+    let timed_out = || std::time::Instant::now() >= deadline;
+    if timed_out() {
+        return Err(SortTimeoutError::TimedOut);
+    }
+    let mut chunks = parse_message(msg)?;
+    if is_sorted(&chunks) {
+        return Ok(Cow::Borrowed(msg));
+    }
+    sort_chunks(&mut chunks);
+    if timed_out() {
+        return Err(SortTimeoutError::TimedOut);
+    }
+    Ok(Cow::Owned(assemble(&chunks, msg)))
+}
+
+/// Message of a panic caught by [`std::panic::catch_unwind`]
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_owned()
+    }
+}
+
+/// Sort fields in the given protobuf message on a separate thread named "protofixer-sort".
+///
+/// Panics if the thread can't be spawned, like [`std::thread::spawn`] does.
+#[cfg(feature = "threading")]
+pub fn sort_protobuf_message_in_thread(msg: Vec<u8>) -> std::thread::JoinHandle<Result<Vec<u8>, ParseError>> {
+    std::thread::Builder::new()
+        .name("protofixer-sort".to_owned())
+        .spawn(move || {
+            let mut msg = msg;
+            sort_protobuf_message_inplace(&mut msg)?;
+            Ok(msg)
+        })
+        .expect("failed to spawn thread")
+}
+
+/// Same as [`sort_protobuf_message`], but returns an [`anyhow::Error`] with context.
+#[cfg(feature = "anyhow")]
+pub fn sort_protobuf_message_anyhow(msg: &[u8]) -> anyhow::Result<Cow<'_, [u8]>> {
+    use anyhow::Context;
+
+    sort_protobuf_message(msg).context("failed to parse protobuf message for sorting")
+}
+
+/// Parse, sort and reassemble the message unconditionally, for benchmarking only.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub fn _bench_sort_message_internal(msg: &[u8]) -> Vec<u8> {
+    let mut chunks = parse_message(msg).expect("bad benchmark message");
+    do_sort(&mut chunks, msg)
 }
 
 struct Chunk {
-    id: u128,
+    id: u32,
+    wire_type: u8,
     offset: usize,
+    /// Length of the tag and (for length-delimited fields) the length prefix
+    header_length: usize,
     length: usize,
 }
 
+impl Chunk {
+    fn bytes<'a>(&self, msg: &'a [u8]) -> &'a [u8] {
+        &msg[self.offset..self.offset + self.length]
+    }
+
+    fn payload<'a>(&self, msg: &'a [u8]) -> &'a [u8] {
+        &msg[self.offset + self.header_length..self.offset + self.length]
+    }
+
+    fn tag_length(&self, msg: &[u8]) -> usize {
+        // Chunk tag has been successfully parsed already
+        read_varint(&msg[self.offset..]).map_or(0, |(_, len)| len)
+    }
+}
+
 // Function [T]::is_sorted() is still unstable (as of Rust 1.61), so need this
 fn is_sorted(chunks: &[Chunk]) -> bool {
     if chunks.is_empty() {
         return true;
     }
     let mut prev = chunks[0].id;
-    for i in 1..chunks.len() {
-        let cur = chunks[i].id;
+    for ck in &chunks[1..] {
+        let cur = ck.id;
         if cur < prev {
             return false;
         }
@@ -58,73 +698,241 @@ fn is_sorted(chunks: &[Chunk]) -> bool {
     true
 }
 
-fn do_sort(chunks: &mut [Chunk], msg: &[u8]) -> Vec<u8> {
+/// Sort chunks in the "canonical" order (by field ID)
+fn sort_chunks(chunks: &mut [Chunk]) {
+    // Sort must be stable: the order of repeated field values is meaningful
     chunks.sort_by_key(|ck| ck.id);
+}
+
+/// Parse protobuf message and sort its chunks in the "canonical" order (by field ID)
+fn sorted_chunks(msg: &[u8]) -> Result<ChunkVec, ParseError> {
+    let mut chunks = parse_message(msg)?;
+    sort_chunks(&mut chunks);
+    Ok(chunks)
+}
+
+fn do_sort(chunks: &mut [Chunk], msg: &[u8]) -> Vec<u8> {
+    sort_chunks(chunks);
+    assemble(chunks, msg)
+}
+
+/// Concatenate chunks bytes in the given order
+fn assemble(chunks: &[Chunk], msg: &[u8]) -> Vec<u8> {
     let mut sorted = Vec::with_capacity(msg.len());
     for ck in chunks.iter() {
-        let start = ck.offset;
-        let end = ck.offset + ck.length;
-        let chunk = &msg[start..end];
-        sorted.extend_from_slice(chunk);
+        sorted.extend_from_slice(ck.bytes(msg));
     }
     sorted
 }
 
+/// Stable sort of chunks by an arbitrary key, the message is borrowed if it's already ordered
+fn reorder_chunks<K: Ord>(msg: &[u8], mut chunks: ChunkVec, key: impl Fn(&Chunk) -> K) -> Cow<'_, [u8]> {
+    if chunks.windows(2).all(|w| key(&w[0]) <= key(&w[1])) {
+        return Cow::Borrowed(msg);
+    }
+    chunks.sort_by_key(&key);
+    Cow::Owned(assemble(&chunks, msg))
+}
+
+/// Chunk list of a parsed message, small messages don't allocate it on the heap
+#[cfg(feature = "smallvec-optimization")]
+type ChunkVec = smallvec::SmallVec<[Chunk; 32]>;
+#[cfg(not(feature = "smallvec-optimization"))]
+type ChunkVec = Vec<Chunk>;
+
+/// Report a sorted message to the `metrics` recorder
+#[cfg(feature = "metrics")]
+fn record_sort_metrics(msg_len: usize, field_count: usize) {
+    metrics::counter!("protofixer.messages_sorted_total").increment(1);
+    metrics::histogram!("protofixer.message_size_bytes").record(msg_len as f64);
+    metrics::histogram!("protofixer.field_count").record(field_count as f64);
+}
+
 /// Parse protobuf message and split it into chunks that can be reordered
-fn parse_message(msg: &[u8]) -> Result<Vec<Chunk>, ParseError> {
-    let mut chunks = Vec::new();
-    let mut bytes = msg;
-    let mut offset = 0;
-    while bytes.len() > 0 {
-        let (key, len) = read_varint(bytes)?;
-        let (field_id, wire_type) = (key >> 3, key & 0x7);
-        let field_length = match wire_type {
-            0 => {
-                // Varint
-                let (_, len) = read_varint(&bytes[len..])?;
-                len
-            }
-            1 => {
-                // 64-bit
-                8
-            }
-            2 => {
-                // Length-delimited
-                let (value, len) = read_varint(&bytes[len..])?;
-                if value > usize::MAX as u128 {
-                    // Too big data length
-                    return Err(ParseError);
-                }
-                value as usize + len
-            }
-            3 | 4 => {
-                // Deprecated stuff, not supported
-                return Err(ParseError);
-            }
-            5 => {
-                // 32-bit
-                4
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(field_count = tracing::field::Empty))
+)]
+fn parse_message(msg: &[u8]) -> Result<ChunkVec, ParseError> {
+    let chunks = parse_message_with_limit(msg, usize::MAX, None)?;
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record("field_count", chunks.len());
+    Ok(chunks)
+}
+
+/// Same as [`parse_message`], but fails if the message has more than `max_fields` fields,
+/// and parses fields with non-standard wire types using the custom handler, if any
+fn parse_message_with_limit(
+    msg: &[u8],
+    max_fields: usize,
+    custom_wire_type_handler: Option<CustomWireTypeHandler>,
+) -> Result<ChunkVec, ParseError> {
+    let mut chunks = ChunkVec::new();
+    let mut parser = PullParser {
+        custom_wire_type_handler,
+        ..PullParser::new(msg)
+    };
+    while let Some(chunk) = parser.next_chunk() {
+        if chunks.len() == max_fields {
+            // Too many fields
+            return Err(ParseError);
+        }
+        chunks.push(chunk?);
+    }
+    debug_assert_eq!(
+        parser.offset,
+        msg.len(),
+        "parsed fields length doesn't match message length, the wire type length calculation is wrong"
+    );
+    Ok(chunks)
+}
+
+/// Cursor over the fields of a protobuf message, parsing them on demand
+struct PullParser<'a> {
+    msg: &'a [u8],
+    offset: usize,
+    custom_wire_type_handler: Option<CustomWireTypeHandler>,
+}
+
+impl<'a> PullParser<'a> {
+    fn new(msg: &'a [u8]) -> Self {
+        PullParser {
+            msg,
+            offset: 0,
+            custom_wire_type_handler: None,
+        }
+    }
+
+    /// Parse the next field, `None` at the end of the message or after a parse error
+    fn next_chunk(&mut self) -> Option<Result<Chunk, ParseError>> {
+        if self.offset >= self.msg.len() {
+            return None;
+        }
+        match parse_field_with(self.msg, self.offset, self.custom_wire_type_handler) {
+            Ok(chunk) => {
+                self.offset += chunk.length;
+                Some(Ok(chunk))
             }
-            _ => {
-                // Unrecognized wire type
-                return Err(ParseError);
+            Err(err) => {
+                self.offset = self.msg.len();
+                Some(Err(err))
             }
-        };
-        let total_length = len + field_length;
-        let chunk = Chunk {
-            id: field_id,
-            offset,
-            length: total_length,
-        };
-        offset += total_length;
-        bytes = &msg[offset..];
-        chunks.push(chunk);
+        }
     }
-    Ok(chunks)
+}
+
+impl Iterator for PullParser<'_> {
+    type Item = Result<Chunk, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk()
+    }
+}
+
+/// Parse a single field of the protobuf message starting at the given offset
+fn parse_field(msg: &[u8], offset: usize) -> Result<Chunk, ParseError> {
+    parse_field_with(msg, offset, None)
+}
+
+/// Same as [`parse_field`], but a field with a non-standard wire type (6 or 7) is parsed
+/// with the custom handler, if any
+fn parse_field_with(
+    msg: &[u8],
+    offset: usize,
+    custom_wire_type_handler: Option<CustomWireTypeHandler>,
+) -> Result<Chunk, ParseError> {
+    let chunk = parse_field_header(msg, offset, custom_wire_type_handler)?;
+    if chunk.offset + chunk.length > msg.len() {
+        // Field length points beyond the end of the message
+        return Err(ParseError);
+    }
+    Ok(chunk)
+}
+
+/// Parse the tag and the length of a field starting at the given offset, the parsed field
+/// may extend beyond the end of `msg`
+fn parse_field_header(
+    msg: &[u8],
+    offset: usize,
+    custom_wire_type_handler: Option<CustomWireTypeHandler>,
+) -> Result<Chunk, ParseError> {
+    let (key, len) = check_varint_range(&msg[offset..])?;
+    let (field_id, wire_type) = (key >> 3, key & 0x7);
+    if field_id == 0 || field_id > MAX_FIELD_ID {
+        // Field number 0 is reserved, and the largest one is 2^29 - 1
+        return Err(ParseError);
+    }
+    // Slice length never exceeds isize::MAX, so adding small numbers to the cursor can't overflow
+    let mut cursor = offset + len;
+    let mut header_length = len;
+    match wire_type {
+        0 => {
+            // Varint. The cursor can't pass the end after reading the tag, but stay defensive
+            let (_, len) = check_varint_range(msg.get(cursor..).ok_or(ParseError)?)?;
+            cursor += len;
+        }
+        1 => {
+            // 64-bit
+            cursor += 8;
+        }
+        2 => {
+            // Length-delimited
+            let (value, len) = check_varint_range(msg.get(cursor..).ok_or(ParseError)?)?;
+            // Too big data length, on 32-bit targets it may fit in u32 and still overflow the cursor
+            let data_length = usize::try_from(value).map_err(|_| ParseError)?;
+            cursor += len;
+            header_length = cursor - offset;
+            cursor = cursor.checked_add(data_length).ok_or(ParseError)?;
+        }
+        3 | 4 => {
+            // Deprecated stuff, not supported
+            return Err(ParseError);
+        }
+        5 => {
+            // 32-bit
+            cursor += 4;
+        }
+        _ => {
+            // Non-standard wire type, unrecognized without a custom handler
+            let handler = custom_wire_type_handler.ok_or(ParseError)?;
+            let length = handler(wire_type as u8, msg.get(cursor..).ok_or(ParseError)?)?;
+            cursor = cursor.checked_add(length).ok_or(ParseError)?;
+        }
+    }
+    Ok(Chunk {
+        id: field_id as u32,
+        wire_type: wire_type as u8,
+        offset,
+        header_length,
+        length: cursor - offset,
+    })
 }
 
 #[inline]
 fn read_varint(bytes: &[u8]) -> Result<(u128, usize), ParseError> {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if let Some(varint) = simd::read_varint(bytes) {
+        return Ok(varint);
+    }
+    read_varint_scalar(bytes)
+}
+
+/// Same as [`read_varint`], but fails if the value doesn't fit in 64 bits, as protobuf requires
+fn check_varint_range(bytes: &[u8]) -> Result<(u64, usize), ParseError> {
+    let (value, len) = read_varint(bytes)?;
+    if !varint_fits_u64(value) {
+        return Err(ParseError);
+    }
+    Ok((value as u64, len))
+}
+
+/// Checks the value read by [`read_varint`], which can hold up to 70 bits
+const fn varint_fits_u64(value: u128) -> bool {
+    value <= u64::MAX as u128
+}
+
+#[inline]
+const fn read_varint_scalar(bytes: &[u8]) -> Result<(u128, usize), ParseError> {
     let buf_size = bytes.len();
     if buf_size == 0 {
         // No data
@@ -132,23 +940,76 @@ fn read_varint(bytes: &[u8]) -> Result<(u128, usize), ParseError> {
     }
     let (mut data, mut offset) = (0, 0);
     while offset < buf_size {
+        if offset == MAX_VARINT_LENGTH {
+            // Continuation bit set in the last allowed byte
+            return Err(ParseError);
+        }
         let byte = bytes[offset];
         offset += 1;
-        data = (data << 7) | (byte & 0x7F) as u128;
+        // Varints are little-endian: each next byte carries the next 7 higher bits
+        data |= ((byte & 0x7F) as u128) << (7 * (offset - 1));
         if byte & 0x80 == 0 {
             // Last byte
-            break;
+            return Ok((data, offset));
         }
     }
-    let varint_length = offset;
-    Ok((data, varint_length))
+    // The last byte has continuation bit set: truncated varint
+    Err(ParseError)
+}
+
+/// Append the given value to the buffer as a minimal-length varint
+fn write_varint(mut value: u128, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
 }
 
 //noinspection SpellCheckingInspection
 #[cfg(test)]
 mod tests {
-    use super::{is_protobuf_message_sorted, parse_message, sort_protobuf_message, sort_protobuf_message_inplace};
+    use super::{
+        assert_protobuf_message_sorted, check_or_sort_inplace, check_varint_range, into_canonical_bytes,
+        is_empty_protobuf_message, is_protobuf_message_sorted, is_protobuf_message_sorted_unchecked,
+        is_single_field_protobuf_message, panic_message, parse_message, sort_protobuf_arc, sort_protobuf_message,
+        sort_protobuf_message_from_iter, sort_protobuf_message_inplace, sort_protobuf_message_lenient,
+        sort_protobuf_message_panic_safe, sort_protobuf_message_repeated_stable, sort_protobuf_message_reusing_buffer,
+        sort_protobuf_message_shared_err, sort_protobuf_message_with_context, sort_protobuf_message_with_field_mask,
+        sort_protobuf_message_with_prefix, sort_protobuf_message_with_retry, sort_protobuf_message_with_timeout,
+        sort_protobuf_message_with_timestamp, sort_protobuf_message_with_trailer, SortPanicError, SortTimeoutError,
+        SortWarning,
+    };
     use lazy_static::lazy_static;
+    use static_assertions::assert_impl_all;
+    use std::borrow::Cow;
+    use std::sync::Arc;
+
+    assert_impl_all!(crate::ParseError: Send, Sync);
+    assert_impl_all!(crate::ContextualParseError<String>: Send, Sync);
+    assert_impl_all!(crate::SortPanicError: Send, Sync);
+    assert_impl_all!(crate::SortWarning: Send, Sync);
+    assert_impl_all!(crate::EncodeError: Send, Sync);
+    assert_impl_all!(crate::Field<'static>: Send, Sync);
+    assert_impl_all!(crate::FieldDecoder<'static>: Send, Sync);
+    assert_impl_all!(crate::MessageIndex<'static>: Send, Sync);
+    assert_impl_all!(crate::ChunkedSortedMessage<'static>: Send, Sync);
+    assert_impl_all!(crate::MessageBuilder: Send, Sync);
+    assert_impl_all!(crate::SortedMessageBytes: Send, Sync);
+    assert_impl_all!(crate::MessageDiff: Send, Sync);
+    assert_impl_all!(crate::MessageFieldSet: Send, Sync);
+    assert_impl_all!(crate::MessageRewriter: Send, Sync);
+    assert_impl_all!(crate::MessageSchema: Send, Sync);
+    assert_impl_all!(crate::SortOptions: Send, Sync);
+    assert_impl_all!(crate::SortReport: Send, Sync);
+    assert_impl_all!(crate::Proto3Schema: Send, Sync);
+    assert_impl_all!(crate::SortFileError: Send, Sync);
+    assert_impl_all!(crate::TransformError: Send, Sync);
+    assert_impl_all!(crate::ComposedValidator: Send, Sync);
+    assert_impl_all!(crate::LazyMessage<'static>: Send, Sync);
+    assert_impl_all!(crate::MessageProcessor: Send, Sync);
+    #[cfg(feature = "blake3")]
+    assert_impl_all!(crate::MessageId: Send, Sync);
 
     lazy_static! {
         /// This protobuf message has fields ordered by ID (consider this canonical)
@@ -179,13 +1040,150 @@ mod tests {
         assert!(parse_message(&[]).is_ok());
         assert!(parse_message(&CANONICAL_FIELD_ORDER).is_ok());
         assert!(parse_message(&NON_CANONICAL_FIELD_ORDER).is_ok());
+        // Truncated fields
+        assert!(parse_message(&[0x09, 0x01, 0x02]).is_err());
+        assert!(parse_message(&[0x0a, 0x05, 0x01]).is_err());
+        assert!(parse_message(&[0x0d, 0x01]).is_err());
+        // Field 0
+        assert!(parse_message(&[0x00, 0x00]).is_err());
+        assert!(parse_message(&[0x08, 0x01, 0x02, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_check_varint_range() {
+        let mut max = vec![0xff; 9];
+        max.push(0x01);
+        assert_eq!(check_varint_range(&max).unwrap(), (u64::MAX, 10));
+        // 2^64
+        let mut too_big = vec![0x80; 9];
+        too_big.push(0x02);
+        assert!(check_varint_range(&too_big).is_err());
+        // Overlong 10-byte encoding of 1 is fine, 11-byte encoding of 0 is too long
+        assert_eq!(
+            check_varint_range(&[0x81, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00]).unwrap(),
+            (1, 10)
+        );
+        let mut zero = vec![0x80; 10];
+        zero.push(0x00);
+        assert!(check_varint_range(&zero).is_err());
+        assert!(super::read_varint(&zero).is_err());
+        // Continuation bits never end
+        assert!(super::read_varint(&[0xFF; 1000]).is_err());
+        assert!(parse_message(&[[0x08].as_slice(), &max].concat()).is_ok());
+        assert!(parse_message(&[[0x08].as_slice(), &too_big].concat()).is_err());
+    }
+
+    #[test]
+    fn test_read_varint_little_endian() {
+        // The first byte carries the lowest 7 bits
+        assert_eq!(super::read_varint(&[0x08]).unwrap(), (8, 1));
+        assert_eq!(super::read_varint(&[0x80, 0x01]).unwrap(), (128, 2));
+        assert_eq!(super::read_varint(&[0x80, 0x10]).unwrap(), (2048, 2));
+        // Fields 1, 16, 256
+        let chunks = parse_message(&[0x08, 0x00, 0x80, 0x01, 0x00, 0x80, 0x10, 0x00]).unwrap();
+        assert_eq!(chunks.iter().map(|ck| ck.id).collect::<Vec<_>>(), [1, 16, 256]);
+    }
+
+    #[test]
+    fn test_read_varint_truncated() {
+        // Continuation bit set in the last byte
+        assert!(super::read_varint(&[0xFF]).is_err());
+        assert!(super::read_varint(&[0x96, 0x81]).is_err());
+        assert!(super::read_varint(&[0xFF; 16]).is_err());
+        assert!(super::read_varint_scalar(&[0xFF]).is_err());
+        assert_eq!(super::read_varint(&[0x96, 0x01]).unwrap(), (150, 2));
+    }
+
+    #[test]
+    fn test_parse_message_truncated_varint() {
+        // Truncated tag
+        assert!(parse_message(&[0x80]).is_err());
+        assert!(parse_message(&[0x08, 0x01, 0x8d]).is_err());
+        // Truncated varint value
+        assert!(parse_message(&[0x08, 0x80]).is_err());
+        assert!(parse_message(&[0x08, 0xff, 0xff]).is_err());
+        // Truncated length prefix
+        assert!(parse_message(&[0x0a, 0x80]).is_err());
+        // Truncated tags of 64-bit and 32-bit fields
+        assert!(parse_message(&[0x89]).is_err());
+        assert!(parse_message(&[0x8d, 0x80]).is_err());
+        assert!(sort_protobuf_message(&[0x18, 0x01, 0x08, 0x80]).is_err());
+    }
+
+    #[test]
+    fn test_parse_message_field_id_range() {
+        // Field 2^29 - 1 is the largest allowed one
+        let msg = [0xf8, 0xff, 0xff, 0xff, 0x0f, 0x01];
+        assert_eq!(parse_message(&msg).unwrap()[0].id, 536_870_911);
+        assert!(parse_message(&[0x80, 0x80, 0x80, 0x80, 0x10, 0x01]).is_err());
+        assert!(parse_message(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01, 0x01]).is_err());
+        assert!(parse_message(&[0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_field_beyond_end() {
+        // Fields that claim more bytes than left in the message must fail, not panic
+        for msg in [
+            [0x08, 0x01, 0x12, 0x7f, 0x00].as_slice(),
+            &[0x08, 0x01, 0x11, 0x01, 0x02],
+            &[0x08, 0x01, 0x15, 0x01],
+            &[0x12, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x00],
+        ] {
+            assert!(sort_protobuf_message(msg).is_err());
+            assert!(sort_protobuf_message_inplace(&mut msg.to_vec()).is_err());
+            assert!(is_protobuf_message_sorted(msg).is_err());
+            let (sorted, errors) = sort_protobuf_message_lenient(msg);
+            assert!(errors.len() == 1 && sorted.len() < msg.len());
+        }
+    }
+
+    #[test]
+    fn test_parse_message_length_beyond_end() {
+        // Field 2 claims 1000 bytes, but only 10 bytes of field 2 and field 1 follow
+        let mut msg = vec![0x12, 0xe8, 0x07];
+        msg.extend_from_slice(&[b'a'; 8]);
+        msg.extend_from_slice(&[0x08, 0x01]);
+        assert!(parse_message(&msg).is_err());
+        assert!(sort_protobuf_message(&msg).is_err());
+        assert!(is_protobuf_message_sorted(&msg).is_err());
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_parse_message_length_overflow() {
+        // Length-delimited field with the payload length of usize::MAX - 10
+        let msg = hex::decode("0af5ffffffffffffffff01").unwrap();
+        assert!(parse_message(&msg).is_err());
+        // Payload length of u32::MAX - 3, 2^64 and 2^77
+        assert!(parse_message(&hex::decode("0afcffffff0f").unwrap()).is_err());
+        assert!(parse_message(&hex::decode("0a8080808080808080800200").unwrap()).is_err());
+        assert!(parse_message(&hex::decode("0a80808080808080808080800100").unwrap()).is_err());
     }
 
     #[test]
     fn test_is_message_sorted() {
-        assert_eq!(is_protobuf_message_sorted(&[]).unwrap(), true);
-        assert_eq!(is_protobuf_message_sorted(&CANONICAL_FIELD_ORDER).unwrap(), true);
-        assert_eq!(is_protobuf_message_sorted(&NON_CANONICAL_FIELD_ORDER).unwrap(), false);
+        assert!(is_protobuf_message_sorted(&[]).unwrap());
+        // Malformed tail after the first out-of-order field isn't parsed
+        assert!(!is_protobuf_message_sorted(&[0x10, 0x02, 0x08, 0x01, 0x0F]).unwrap());
+        assert!(is_protobuf_message_sorted(&[0x08, 0x01, 0x10, 0x02, 0x0F]).is_err());
+        assert!(is_protobuf_message_sorted(&CANONICAL_FIELD_ORDER).unwrap());
+        assert!(!is_protobuf_message_sorted(&NON_CANONICAL_FIELD_ORDER).unwrap());
+    }
+
+    #[test]
+    fn test_is_message_sorted_unchecked() {
+        assert!(is_protobuf_message_sorted_unchecked(&[]));
+        assert!(is_protobuf_message_sorted_unchecked(&CANONICAL_FIELD_ORDER));
+        assert!(!is_protobuf_message_sorted_unchecked(&NON_CANONICAL_FIELD_ORDER));
+        assert!(!is_protobuf_message_sorted_unchecked(&[0x0F]));
+    }
+
+    #[test]
+    fn test_assert_message_sorted() {
+        assert_protobuf_message_sorted(&[]);
+        assert_protobuf_message_sorted(&CANONICAL_FIELD_ORDER);
+        assert!(std::panic::catch_unwind(|| assert_protobuf_message_sorted(&NON_CANONICAL_FIELD_ORDER)).is_err());
+        assert!(std::panic::catch_unwind(|| assert_protobuf_message_sorted(&[0x0F])).is_err());
     }
 
     #[test]
@@ -200,16 +1198,392 @@ mod tests {
         test(&NON_CANONICAL_FIELD_ORDER, &CANONICAL_FIELD_ORDER);
     }
 
+    #[test]
+    fn test_sort_already_sorted_returns_borrowed() {
+        for msg in [[].as_slice(), &CANONICAL_FIELD_ORDER, &[0x08, 0x02, 0x08, 0x01]] {
+            let sorted = sort_protobuf_message(msg);
+            assert!(matches!(sorted, Ok(Cow::Borrowed(_))), "{:02x?}", msg);
+            assert_eq!(sorted.unwrap().as_ptr(), msg.as_ptr());
+        }
+    }
+
+    #[test]
+    fn test_sort_needs_sorting_returns_owned() {
+        for msg in [NON_CANONICAL_FIELD_ORDER.as_slice(), &[0x10, 0x01, 0x08, 0x02]] {
+            assert!(matches!(sort_protobuf_message(msg), Ok(Cow::Owned(_))), "{:02x?}", msg);
+        }
+    }
+
     #[test]
     fn test_sort_message_inplace() {
-        let test = |msg: &[u8]| {
+        let test = |msg: &[u8], reordered: bool| {
             let mut msg = Vec::from(msg);
-            sort_protobuf_message_inplace(&mut msg).unwrap();
+            assert_eq!(sort_protobuf_message_inplace(&mut msg).unwrap(), reordered);
             assert!(is_protobuf_message_sorted(&msg).unwrap());
             msg
         };
-        assert_eq!(test(&[]), &[]);
-        assert_eq!(test(&CANONICAL_FIELD_ORDER), CANONICAL_FIELD_ORDER.to_vec());
-        assert_eq!(test(&NON_CANONICAL_FIELD_ORDER), CANONICAL_FIELD_ORDER.to_vec());
+        assert!(test(&[], false).is_empty());
+        assert_eq!(test(&CANONICAL_FIELD_ORDER, false), CANONICAL_FIELD_ORDER.to_vec());
+        assert_eq!(test(&NON_CANONICAL_FIELD_ORDER, true), CANONICAL_FIELD_ORDER.to_vec());
+        assert!(sort_protobuf_message_inplace(&mut [0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_with_prefix() {
+        let sorted = sort_protobuf_message_with_prefix(&NON_CANONICAL_FIELD_ORDER, b"\nproto").unwrap();
+        assert_eq!(sorted, [b"\nproto".as_slice(), &CANONICAL_FIELD_ORDER].concat());
+        assert_eq!(sort_protobuf_message_with_prefix(&[], b"v1").unwrap(), b"v1");
+        assert!(sort_protobuf_message_with_prefix(&[0x0F], b"v1").is_err());
+    }
+
+    #[test]
+    fn test_check_or_sort_inplace() {
+        let mut msg = NON_CANONICAL_FIELD_ORDER.clone();
+        assert!(!check_or_sort_inplace(&mut msg).unwrap());
+        assert_eq!(msg, *CANONICAL_FIELD_ORDER);
+        assert!(check_or_sort_inplace(&mut msg).unwrap());
+        assert_eq!(msg, *CANONICAL_FIELD_ORDER);
+        assert!(check_or_sort_inplace(&mut [0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_with_timestamp() {
+        // Fields 3, 1, 2, 2
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02, 0x10, 0x04];
+        assert_eq!(
+            sort_protobuf_message_with_timestamp(&msg, 2, 150).unwrap(),
+            [0x08, 0x01, 0x10, 0x96, 0x01, 0x18, 0x03]
+        );
+        assert_eq!(
+            sort_protobuf_message_with_timestamp(&msg, 4, 1).unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x10, 0x04, 0x18, 0x03, 0x20, 0x01]
+        );
+        assert_eq!(sort_protobuf_message_with_timestamp(&[], 1, 0).unwrap(), [0x08, 0x00]);
+        assert!(sort_protobuf_message_with_timestamp(&msg, 0, 1).is_err());
+        assert!(sort_protobuf_message_with_timestamp(&[0x0F], 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_with_field_mask() {
+        // Fields 3, 1, 2, 3
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02, 0x18, 0x04];
+        assert_eq!(
+            sort_protobuf_message_with_field_mask(&msg, &[3, 1]).unwrap(),
+            [0x08, 0x01, 0x18, 0x03, 0x18, 0x04]
+        );
+        assert!(sort_protobuf_message_with_field_mask(&msg, &[]).unwrap().is_empty());
+        assert!(sort_protobuf_message_with_field_mask(&[0x0F], &[1]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_from_iter() {
+        let msg = &NON_CANONICAL_FIELD_ORDER;
+        let sorted = sort_protobuf_message_from_iter(msg.iter().copied(), msg.len()).unwrap();
+        assert_eq!(sorted, *CANONICAL_FIELD_ORDER);
+        assert!(sort_protobuf_message_from_iter(msg.iter().copied(), msg.len() - 1).is_err());
+        assert!(sort_protobuf_message_from_iter(std::iter::empty(), 0)
+            .unwrap()
+            .is_empty());
+        assert!(sort_protobuf_message_from_iter([0x0F].into_iter(), 10).is_err());
+    }
+
+    #[test]
+    fn test_into_canonical_bytes() {
+        let msg = CANONICAL_FIELD_ORDER.clone();
+        let ptr = msg.as_ptr();
+        let sorted = into_canonical_bytes(msg).unwrap();
+        assert_eq!(sorted.as_ptr(), ptr);
+        let msg = NON_CANONICAL_FIELD_ORDER.clone();
+        let ptr = msg.as_ptr();
+        let sorted = into_canonical_bytes(msg).unwrap();
+        assert_eq!(sorted, *CANONICAL_FIELD_ORDER);
+        assert_eq!(sorted.as_ptr(), ptr);
+        assert_eq!(
+            into_canonical_bytes([0x10, 0x02, 0x08, 0x01]).unwrap(),
+            [0x08, 0x01, 0x10, 0x02]
+        );
+        assert!(into_canonical_bytes(vec![0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_reusing_buffer() {
+        let mut buf = vec![0xff; 3];
+        assert!(sort_protobuf_message_reusing_buffer(&NON_CANONICAL_FIELD_ORDER, &mut buf).unwrap());
+        assert_eq!(buf, *CANONICAL_FIELD_ORDER);
+        assert!(!sort_protobuf_message_reusing_buffer(&CANONICAL_FIELD_ORDER, &mut buf).unwrap());
+        assert_eq!(buf, *CANONICAL_FIELD_ORDER);
+        assert!(!sort_protobuf_message_reusing_buffer(&[], &mut buf).unwrap());
+        assert!(buf.is_empty());
+        assert!(sort_protobuf_message_reusing_buffer(&[0x0F], &mut buf).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_mixed_wire_types() {
+        // Field 2 as packed (2, 3) and unpacked (1) repeated values, interleaved with field 1.
+        // Wire types of the same field ID must keep their relative order, like any repeated values.
+        let msg = [0x12, 0x02, 0x02, 0x03, 0x08, 0x01, 0x10, 0x01];
+        let sorted = sort_protobuf_message(&msg).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x12, 0x02, 0x02, 0x03, 0x10, 0x01].as_slice());
+        assert_eq!(sort_protobuf_message(&sorted).unwrap(), sorted);
+    }
+
+    #[test]
+    fn test_sort_message_repeated_stable() {
+        // Field 5 with values 3, 1, 2 interleaved with fields 7 and 1
+        let msg = [0x28, 0x03, 0x38, 0x00, 0x28, 0x01, 0x08, 0x00, 0x28, 0x02];
+        let sorted = [0x08, 0x00, 0x28, 0x03, 0x28, 0x01, 0x28, 0x02, 0x38, 0x00];
+        assert_eq!(sort_protobuf_message_repeated_stable(&msg).unwrap(), sorted.as_slice());
+        assert_eq!(sort_protobuf_message(&msg).unwrap(), sorted.as_slice());
+        let mut inplace = msg;
+        assert!(sort_protobuf_message_inplace(&mut inplace).unwrap());
+        assert_eq!(inplace, sorted);
+        assert!(sort_protobuf_message_repeated_stable(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_with_trailer() {
+        let test = |msg: &[u8], trailer: &[u8], expected: &[u8]| {
+            let input = [msg, trailer].concat();
+            let (sorted, consumed) = sort_protobuf_message_with_trailer(&input);
+            assert_eq!(sorted, expected);
+            assert_eq!(consumed, msg.len());
+        };
+        test(&[], &[], &[]);
+        test(&CANONICAL_FIELD_ORDER, &[], &CANONICAL_FIELD_ORDER);
+        test(&CANONICAL_FIELD_ORDER, &[0x0F, 0x00], &CANONICAL_FIELD_ORDER);
+        test(&NON_CANONICAL_FIELD_ORDER, &[0x0F, 0x00], &CANONICAL_FIELD_ORDER);
+        // Truncated field at the end
+        test(&NON_CANONICAL_FIELD_ORDER, &[0x0a, 0x05, 0x01], &CANONICAL_FIELD_ORDER);
+        // Garbage only: everything is the trailer
+        test(&[], &[0x0F, 0x00], &[]);
+    }
+
+    #[test]
+    fn test_sort_message_with_retry() {
+        let (sorted, warning) = sort_protobuf_message_with_retry(&NON_CANONICAL_FIELD_ORDER).unwrap();
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        assert_eq!(warning, None);
+        let msg = [&NON_CANONICAL_FIELD_ORDER[..], &[0x0a, 0x05, 0x01]].concat();
+        let (sorted, warning) = sort_protobuf_message_with_retry(&msg).unwrap();
+        assert_eq!(sorted, [&CANONICAL_FIELD_ORDER[..], &[0x0a, 0x05, 0x01]].concat());
+        assert_eq!(
+            warning,
+            Some(SortWarning {
+                unsorted_offset: NON_CANONICAL_FIELD_ORDER.len()
+            })
+        );
+        let (sorted, warning) = sort_protobuf_message_with_retry(&[0x08, 0x01, 0x0F]).unwrap();
+        assert!(matches!(sorted, Cow::Borrowed(&[0x08, 0x01, 0x0F])));
+        assert_eq!(warning, Some(SortWarning { unsorted_offset: 2 }));
+        assert!(sort_protobuf_message_with_retry(&[0x0F, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_lenient() {
+        let (sorted, errors) = sort_protobuf_message_lenient(&NON_CANONICAL_FIELD_ORDER);
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        assert!(errors.is_empty());
+        // Fields 3 and 1 separated by garbage, the last field is truncated
+        let msg = [0x18, 0x01, 0x0F, 0x0F, 0x08, 0x02, 0x0d, 0x01];
+        let (sorted, errors) = sort_protobuf_message_lenient(&msg);
+        assert_eq!(sorted, [0x08, 0x02, 0x18, 0x01].as_slice());
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[cfg(feature = "thread-local-opt")]
+    #[test]
+    fn test_sort_message_with_thread_local_buffer() {
+        use super::sort_protobuf_message_with_thread_local_buffer as sort;
+
+        for _ in 0..2 {
+            assert_eq!(
+                sort(&NON_CANONICAL_FIELD_ORDER).unwrap(),
+                CANONICAL_FIELD_ORDER.as_slice()
+            );
+            assert!(matches!(sort(&CANONICAL_FIELD_ORDER).unwrap(), Cow::Borrowed(_)));
+            assert!(sort(&[0x08, 0x01, 0x0F]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_sort_message_with_context() {
+        let sorted = sort_protobuf_message_with_context(&NON_CANONICAL_FIELD_ORDER, "msg #1").unwrap();
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        let err = sort_protobuf_message_with_context(&[0x0F], format!("msg #{}", 2)).unwrap_err();
+        assert_eq!(err.context, "msg #2");
+        assert_eq!(
+            err.to_string(),
+            "failed to parse protobuf message (context: msg #2): failed to parse protobuf message"
+        );
+    }
+
+    #[test]
+    fn test_sort_message_shared_err() {
+        let sorted = sort_protobuf_message_shared_err(&NON_CANONICAL_FIELD_ORDER).unwrap();
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        let err = sort_protobuf_message_shared_err(&[0x0F]).unwrap_err();
+        let shared = Arc::clone(&err);
+        assert!(Arc::ptr_eq(&err, &shared));
+    }
+
+    #[test]
+    fn test_sort_message_with_timeout() {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(3600);
+        let sorted = sort_protobuf_message_with_timeout(&NON_CANONICAL_FIELD_ORDER, deadline).unwrap();
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        assert!(matches!(
+            sort_protobuf_message_with_timeout(&[0x0F], deadline),
+            Err(SortTimeoutError::Parse(_))
+        ));
+        assert!(matches!(
+            sort_protobuf_message_with_timeout(&CANONICAL_FIELD_ORDER, std::time::Instant::now()),
+            Err(SortTimeoutError::TimedOut)
+        ));
+    }
+
+    #[test]
+    fn test_sort_message_panic_safe() {
+        let sorted = sort_protobuf_message_panic_safe(&NON_CANONICAL_FIELD_ORDER).unwrap();
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        assert!(matches!(
+            sort_protobuf_message_panic_safe(&[0x0F]),
+            Err(SortPanicError::Parse(_))
+        ));
+        let payload = std::panic::catch_unwind(|| panic!("field {} is broken", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "field 1 is broken");
+    }
+
+    #[test]
+    fn test_sort_arc() {
+        let canonical: Arc<[u8]> = Arc::from(CANONICAL_FIELD_ORDER.as_slice());
+        let sorted = sort_protobuf_arc(canonical.clone()).unwrap();
+        assert!(Arc::ptr_eq(&sorted, &canonical));
+        let sorted = sort_protobuf_arc(Arc::from(NON_CANONICAL_FIELD_ORDER.as_slice())).unwrap();
+        assert_eq!(sorted, canonical);
+        assert!(sort_protobuf_arc(Arc::from([0x0F].as_slice())).is_err());
+    }
+
+    #[cfg(feature = "threading")]
+    #[test]
+    fn test_sort_message_in_thread() {
+        let handle = super::sort_protobuf_message_in_thread(NON_CANONICAL_FIELD_ORDER.clone());
+        assert_eq!(handle.thread().name(), Some("protofixer-sort"));
+        assert_eq!(handle.join().unwrap().unwrap(), CANONICAL_FIELD_ORDER.to_vec());
+        assert!(super::sort_protobuf_message_in_thread(vec![0x0F])
+            .join()
+            .unwrap()
+            .is_err());
+    }
+
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn test_sort_message_anyhow() {
+        let sorted = super::sort_protobuf_message_anyhow(&NON_CANONICAL_FIELD_ORDER).unwrap();
+        assert_eq!(sorted, CANONICAL_FIELD_ORDER.as_slice());
+        let err = super::sort_protobuf_message_anyhow(&[0x0F]).unwrap_err();
+        assert_eq!(err.to_string(), "failed to parse protobuf message for sorting");
+        assert!(err.root_cause().is::<super::ParseError>());
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_parse_error_diagnostic() {
+        use miette::Diagnostic;
+
+        let err = sort_protobuf_message(&[0x0F]).unwrap_err();
+        assert_eq!(err.code().unwrap().to_string(), "protofixer::parse::failed");
+        assert_eq!(
+            err.help().unwrap().to_string(),
+            "ensure the input is a valid proto3 wire-format encoded message"
+        );
+        assert!(err.labels().is_none());
+        let report = miette::Report::new(err);
+        assert_eq!(report.to_string(), "failed to parse protobuf message");
+    }
+
+    #[test]
+    fn test_empty_and_single_field_message() {
+        assert!(is_empty_protobuf_message(&[]));
+        assert!(!is_empty_protobuf_message(&[0x08, 0x01]));
+        assert!(matches!(sort_protobuf_message(&[]).unwrap(), Cow::Borrowed([])));
+        assert!(!is_single_field_protobuf_message(&[]).unwrap());
+        assert!(is_single_field_protobuf_message(&[0x08, 0x01]).unwrap());
+        assert!(is_single_field_protobuf_message(&[0x0a, 0x02, 0x08, 0x01]).unwrap());
+        assert!(!is_single_field_protobuf_message(&[0x08, 0x01, 0x08, 0x02]).unwrap());
+        assert!(!is_single_field_protobuf_message(&[0x08, 0x01, 0x0F]).unwrap());
+        assert!(is_single_field_protobuf_message(&[0x0F]).is_err());
+    }
+
+    mod wire_type_coverage {
+        use crate::sort_protobuf_message;
+
+        /// Sort the message, check the result and that sorting it again doesn't change it
+        fn check_sort(msg: &[u8], expected: &[u8]) {
+            let sorted = sort_protobuf_message(msg).unwrap();
+            assert_eq!(sorted, expected);
+            assert_eq!(sort_protobuf_message(&sorted).unwrap(), sorted);
+        }
+
+        #[test]
+        fn test_varint_fields_only() {
+            // Fields 3, 1 (multi-byte value), 2
+            check_sort(
+                &[0x18, 0x03, 0x08, 0x96, 0x01, 0x10, 0x02],
+                &[0x08, 0x96, 0x01, 0x10, 0x02, 0x18, 0x03],
+            );
+        }
+
+        #[test]
+        fn test_fixed64_fields_only() {
+            let msg = [[0x11, 2, 0, 0, 0, 0, 0, 0, 0], [0x09, 1, 0, 0, 0, 0, 0, 0, 0]].concat();
+            let expected = [[0x09, 1, 0, 0, 0, 0, 0, 0, 0], [0x11, 2, 0, 0, 0, 0, 0, 0, 0]].concat();
+            check_sort(&msg, &expected);
+        }
+
+        #[test]
+        fn test_length_delimited_fields_only() {
+            // Fields 2, 1 (empty), 3
+            check_sort(
+                &[0x12, 0x02, b'a', b'b', 0x0a, 0x00, 0x1a, 0x01, b'c'],
+                &[0x0a, 0x00, 0x12, 0x02, b'a', b'b', 0x1a, 0x01, b'c'],
+            );
+        }
+
+        #[test]
+        fn test_fixed32_fields_only() {
+            check_sort(
+                &[0x1d, 3, 0, 0, 0, 0x0d, 1, 0, 0, 0],
+                &[0x0d, 1, 0, 0, 0, 0x1d, 3, 0, 0, 0],
+            );
+        }
+
+        #[test]
+        fn test_mixed_wire_types_interleaved() {
+            // Fields 5 (fixed32), 2 (length-delimited), 4 (fixed64), 1 (varint), 2 (varint)
+            let msg = [
+                [0x2d, 5, 0, 0, 0].as_slice(),
+                &[0x12, 0x01, b'x'],
+                &[0x21, 4, 0, 0, 0, 0, 0, 0, 0],
+                &[0x08, 0x01],
+                &[0x10, 0x07],
+            ]
+            .concat();
+            let expected = [
+                [0x08, 0x01].as_slice(),
+                &[0x12, 0x01, b'x'],
+                &[0x10, 0x07],
+                &[0x21, 4, 0, 0, 0, 0, 0, 0, 0],
+                &[0x2d, 5, 0, 0, 0],
+            ]
+            .concat();
+            check_sort(&msg, &expected);
+        }
+
+        #[test]
+        fn test_group_wire_types_rejected() {
+            // Deprecated start and end group wire types (3 and 4) aren't supported
+            assert!(sort_protobuf_message(&[0x0b, 0x0c]).is_err());
+            assert!(sort_protobuf_message(&[0x08, 0x01, 0x0c]).is_err());
+        }
     }
 }
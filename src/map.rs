@@ -0,0 +1,104 @@
+use crate::{parse_message, read_varint, ParseError};
+use std::borrow::Cow;
+
+/// Key of a map entry: integer keys are compared as unsigned numbers, string keys bytewise
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MapKey<'a> {
+    Missing,
+    Integer(u128),
+    Bytes(&'a [u8]),
+}
+
+/// Sort entries of the map field `map_field_id` of the given protobuf message by their keys.
+///
+/// Map entries are embedded messages with the key in field 1 and the value in field 2.
+/// The entries are reordered among the positions they occupy, other fields are left intact.
+/// Integer keys are compared as unsigned numbers, so negative `int32`/`int64` keys go last.
+pub fn sort_proto_map_entries(msg: &[u8], map_field_id: u32) -> Result<Cow<'_, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    let mut entries = Vec::new();
+    for ck in chunks.iter().filter(|ck| ck.id == map_field_id) {
+        if ck.wire_type != 2 {
+            // Not a map field
+            return Err(ParseError);
+        }
+        entries.push((map_key(ck.payload(msg))?, ck));
+    }
+    if entries.windows(2).all(|w| w[0].0 <= w[1].0) {
+        return Ok(Cow::Borrowed(msg));
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut entries = entries.into_iter();
+    let mut sorted = Vec::with_capacity(msg.len());
+    for ck in &chunks {
+        let ck = if ck.id == map_field_id {
+            entries.next().expect("same number of entries").1
+        } else {
+            ck
+        };
+        sorted.extend_from_slice(ck.bytes(msg));
+    }
+    Ok(Cow::Owned(sorted))
+}
+
+/// The last occurrence of the key field wins, like protobuf decoders do
+fn map_key(entry: &[u8]) -> Result<MapKey<'_>, ParseError> {
+    let chunks = parse_message(entry)?;
+    let Some(ck) = chunks.iter().rev().find(|ck| ck.id == 1) else {
+        return Ok(MapKey::Missing);
+    };
+    let payload = ck.payload(entry);
+    Ok(match ck.wire_type {
+        0 => MapKey::Integer(read_varint(payload)?.0),
+        2 => MapKey::Bytes(payload),
+        _ => MapKey::Integer(payload.iter().rev().fold(0, |acc, &b| acc << 8 | u128::from(b))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_proto_map_entries;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_sort_proto_map_entries() {
+        // Field 1, entries {"b": 1}, {"a": 2} of map field 3, field 2, entry {value: 3} without key
+        let msg = [
+            0x08, 0x01, 0x1a, 0x05, 0x0a, 0x01, b'b', 0x10, 0x01, 0x1a, 0x05, 0x0a, 0x01, b'a', 0x10, 0x02, 0x10, 0x02,
+            0x1a, 0x02, 0x10, 0x03,
+        ];
+        let sorted = sort_proto_map_entries(&msg, 3).unwrap();
+        assert_eq!(
+            sorted,
+            [
+                0x08, 0x01, 0x1a, 0x02, 0x10, 0x03, 0x1a, 0x05, 0x0a, 0x01, b'a', 0x10, 0x02, 0x10, 0x02, 0x1a, 0x05,
+                0x0a, 0x01, b'b', 0x10, 0x01,
+            ]
+            .as_slice()
+        );
+        assert!(matches!(sort_proto_map_entries(&sorted, 3).unwrap(), Cow::Borrowed(_)));
+        assert!(matches!(sort_proto_map_entries(&msg, 4).unwrap(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_sort_proto_map_entries_integer_keys() {
+        // Entries {300: ""}, {5: ""} with varint keys, {2: ""}, {1: ""} with fixed32 keys
+        let msg = [0x0a, 0x03, 0x08, 0xac, 0x02, 0x0a, 0x02, 0x08, 0x05];
+        assert_eq!(
+            sort_proto_map_entries(&msg, 1).unwrap(),
+            [0x0a, 0x02, 0x08, 0x05, 0x0a, 0x03, 0x08, 0xac, 0x02].as_slice()
+        );
+        let msg = [0x0a, 0x05, 0x0d, 2, 0, 0, 0, 0x0a, 0x05, 0x0d, 1, 0, 0, 0];
+        assert_eq!(
+            sort_proto_map_entries(&msg, 1).unwrap(),
+            [0x0a, 0x05, 0x0d, 1, 0, 0, 0, 0x0a, 0x05, 0x0d, 2, 0, 0, 0].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_sort_proto_map_entries_errors() {
+        assert!(sort_proto_map_entries(&[0x08, 0x01], 1).is_err());
+        assert!(sort_proto_map_entries(&[0x0a, 0x01, 0x0F], 1).is_err());
+        assert!(sort_proto_map_entries(&[0x0F], 1).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use crate::hex::encode_hex;
+use crate::{sorted_chunks, ParseError};
+use std::io::{self, Write};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NdjsonError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Write fields of the given protobuf message as newline-delimited JSON, one object per field,
+/// sorted in the "canonical" order (by field ID), e.g.:
+/// `{"field_id":1,"wire_type":0,"value_hex":"54"}`.
+///
+/// Values don't include the tag and the length prefix. Nothing is written if the message
+/// is malformed.
+pub fn stream_message_as_ndjson<W: Write>(msg: &[u8], writer: &mut W) -> Result<(), NdjsonError> {
+    for ck in &sorted_chunks(msg)? {
+        writeln!(
+            writer,
+            r#"{{"field_id":{},"wire_type":{},"value_hex":"{}"}}"#,
+            ck.id,
+            ck.wire_type,
+            encode_hex(ck.payload(msg))
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stream_message_as_ndjson, NdjsonError};
+
+    #[test]
+    fn test_stream_message_as_ndjson() {
+        // Fields 3 ("hi"), 1
+        let msg = [0x1a, 0x02, b'h', b'i', 0x08, 0x54];
+        let mut output = Vec::new();
+        stream_message_as_ndjson(&msg, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"{"field_id":1,"wire_type":0,"value_hex":"54"}"#,
+                "\n",
+                r#"{"field_id":3,"wire_type":2,"value_hex":"6869"}"#,
+                "\n"
+            )
+        );
+        let mut output = Vec::new();
+        stream_message_as_ndjson(&[], &mut output).unwrap();
+        assert!(output.is_empty());
+        assert!(matches!(
+            stream_message_as_ndjson(&[0x08, 0x54, 0x0F], &mut output),
+            Err(NdjsonError::Parse(_))
+        ));
+        assert!(output.is_empty());
+        let mut buf = [0u8; 8];
+        assert!(matches!(
+            stream_message_as_ndjson(&msg, &mut buf.as_mut_slice()),
+            Err(NdjsonError::Io(_))
+        ));
+    }
+}
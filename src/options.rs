@@ -0,0 +1,444 @@
+use crate::builder::tag_length;
+use crate::{assemble, parse_message, parse_message_with_limit, write_varint, Chunk, ParseError, MAX_FIELD_ID};
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+use thiserror::Error;
+
+/// Maximum number of fields in a message accepted by [`SortOptions::default()`].
+///
+/// Can be changed at compile time with `PROTOFIXER_MAX_FIELDS` environment variable.
+pub const DEFAULT_MAX_FIELDS: usize = match option_env!("PROTOFIXER_MAX_FIELDS") {
+    Some(value) => parse_usize(value),
+    None => 65536,
+};
+
+const fn parse_usize(value: &str) -> usize {
+    let bytes = value.as_bytes();
+    if bytes.is_empty() {
+        panic!("PROTOFIXER_MAX_FIELDS must be a decimal number");
+    }
+    let mut result: usize = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let digit = bytes[i];
+        if !digit.is_ascii_digit() {
+            panic!("PROTOFIXER_MAX_FIELDS must be a decimal number");
+        }
+        result = match result.checked_mul(10) {
+            Some(value) => match value.checked_add((digit - b'0') as usize) {
+                Some(value) => value,
+                None => panic!("PROTOFIXER_MAX_FIELDS is too big"),
+            },
+            None => panic!("PROTOFIXER_MAX_FIELDS is too big"),
+        };
+        i += 1;
+    }
+    result
+}
+
+/// Field numbers conventionally reserved for proto2 extensions, e.g. by `descriptor.proto` options
+const DEFAULT_EXTENSION_RANGE: RangeInclusive<u32> = 1000..=MAX_FIELD_ID as u32;
+
+/// Check whether the field ID is in the conventional proto2 extension range, 1000 and above,
+/// like `extensions 1000 to max;` in `descriptor.proto`.
+pub fn is_extension_field(field_id: u32) -> bool {
+    DEFAULT_EXTENSION_RANGE.contains(&field_id)
+}
+
+/// Field numbers reserved for the protobuf implementation, not allowed in `.proto` files
+const RESERVED_FIELD_NUMBERS: RangeInclusive<u32> = 19000..=19999;
+
+/// Check whether the given protobuf message has fields in the 19000-19999 range reserved
+/// for the protobuf implementation, which likely indicates an encoding error.
+pub fn has_reserved_field_numbers(msg: &[u8]) -> Result<bool, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(chunks.iter().any(|ck| RESERVED_FIELD_NUMBERS.contains(&ck.id)))
+}
+
+/// Reserved field numbers (see [`has_reserved_field_numbers`]) used in the given protobuf
+/// message, in ascending order without duplicates.
+pub fn reserved_field_numbers(msg: &[u8]) -> Result<Vec<u32>, ParseError> {
+    let chunks = parse_message(msg)?;
+    let mut ids: Vec<u32> = chunks
+        .iter()
+        .map(|ck| ck.id)
+        .filter(|id| RESERVED_FIELD_NUMBERS.contains(id))
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Error of [`sort_protobuf_message_with_options`]
+#[derive(Debug, Clone, Error)]
+pub enum SortError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("sorted protobuf message of {size} bytes exceeds the limit of {limit} bytes")]
+    OutputTooLarge { size: usize, limit: usize },
+}
+
+/// Parser of fields with a non-standard wire type (6 or 7), see
+/// [`SortOptions::custom_wire_type_handler`].
+///
+/// Gets the wire type and the message bytes after the field tag, returns the length of the field
+/// after the tag.
+pub type CustomWireTypeHandler = fn(wire_type: u8, remaining_bytes: &[u8]) -> Result<usize, ParseError>;
+
+/// Options for [`sort_protobuf_message_with_options`].
+#[derive(Debug, Clone)]
+pub struct SortOptions {
+    max_fields: usize,
+    minimize_tag_varints: bool,
+    treat_extensions_as_unknown: bool,
+    extension_ranges: Vec<RangeInclusive<u32>>,
+    stable: bool,
+    reject_reserved_field_numbers: bool,
+    max_output_bytes: usize,
+    custom_wire_type_handler: Option<CustomWireTypeHandler>,
+}
+
+impl Default for SortOptions {
+    fn default() -> Self {
+        SortOptions {
+            max_fields: DEFAULT_MAX_FIELDS,
+            minimize_tag_varints: false,
+            treat_extensions_as_unknown: false,
+            extension_ranges: vec![DEFAULT_EXTENSION_RANGE],
+            stable: true,
+            reject_reserved_field_numbers: false,
+            max_output_bytes: usize::MAX,
+            custom_wire_type_handler: None,
+        }
+    }
+}
+
+impl SortOptions {
+    /// Maximum number of fields in a message, messages with more fields are rejected
+    /// as malformed. Defaults to [`DEFAULT_MAX_FIELDS`].
+    pub fn max_fields(mut self, n: usize) -> Self {
+        self.max_fields = n;
+        self
+    }
+
+    /// Re-encode overlong field tags as minimal-length varints. Disabled by default,
+    /// i.e. the tag bytes are kept as is.
+    pub fn minimize_tag_varints(mut self, enabled: bool) -> Self {
+        self.minimize_tag_varints = enabled;
+        self
+    }
+
+    /// Put proto2 extension fields after all the other fields, each group sorted by field ID.
+    /// Disabled by default.
+    pub fn treat_extensions_as_unknown(mut self, enabled: bool) -> Self {
+        self.treat_extensions_as_unknown = enabled;
+        self
+    }
+
+    /// Field ID ranges declared with `extensions` in the message schema.
+    /// Defaults to the conventional range, see [`is_extension_field`].
+    pub fn extension_ranges(mut self, ranges: impl IntoIterator<Item = RangeInclusive<u32>>) -> Self {
+        self.extension_ranges = ranges.into_iter().collect();
+        self
+    }
+
+    /// Keep the relative order of repeated fields (same field ID). Enabled by default.
+    ///
+    /// Disabling it uses unstable sort, which is typically 10-20% faster on randomly ordered
+    /// fields, but may reorder the values of repeated fields, changing the message meaning.
+    pub fn stable(mut self, enabled: bool) -> Self {
+        self.stable = enabled;
+        self
+    }
+
+    /// Reject messages with fields in the reserved 19000-19999 range as malformed,
+    /// see [`has_reserved_field_numbers`]. Disabled by default.
+    pub fn reject_reserved_field_numbers(mut self, enabled: bool) -> Self {
+        self.reject_reserved_field_numbers = enabled;
+        self
+    }
+
+    /// Maximum size of the sorted message, larger ones fail with [`SortError::OutputTooLarge`].
+    /// Unlimited by default.
+    ///
+    /// Sorting never grows a message, so unless tags are minimized, this is the same as limiting
+    /// the input size, and the check is done before parsing.
+    pub fn max_output_bytes(mut self, limit: usize) -> Self {
+        self.max_output_bytes = limit;
+        self
+    }
+
+    /// Parse fields with wire types 6 and 7 using the given handler, instead of rejecting
+    /// the message as malformed. Such fields are sorted by field ID like any other.
+    pub fn custom_wire_type_handler(mut self, handler: CustomWireTypeHandler) -> Self {
+        self.custom_wire_type_handler = Some(handler);
+        self
+    }
+
+    fn check_output_size(&self, size: usize) -> Result<(), SortError> {
+        if size > self.max_output_bytes {
+            return Err(SortError::OutputTooLarge {
+                size,
+                limit: self.max_output_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    fn sort_chunks(&self, chunks: &mut [Chunk]) {
+        if self.stable {
+            chunks.sort_by_key(|ck| self.sort_key(ck));
+        } else {
+            chunks.sort_unstable_by_key(|ck| self.sort_key(ck));
+        }
+    }
+
+    /// Key that orders chunks according to the options
+    fn sort_key(&self, ck: &Chunk) -> (bool, u32) {
+        let id = ck.id;
+        let extension = self.treat_extensions_as_unknown && self.extension_ranges.iter().any(|r| r.contains(&id));
+        (extension, ck.id)
+    }
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// with the given options.
+pub fn sort_protobuf_message_with_options<'a>(
+    msg: &'a [u8],
+    options: &SortOptions,
+) -> Result<Cow<'a, [u8]>, SortError> {
+    if !options.minimize_tag_varints {
+        options.check_output_size(msg.len())?;
+    }
+    let mut chunks = parse_message_with_limit(msg, options.max_fields, options.custom_wire_type_handler)?;
+    if options.reject_reserved_field_numbers && chunks.iter().any(|ck| RESERVED_FIELD_NUMBERS.contains(&ck.id)) {
+        return Err(ParseError.into());
+    }
+    let sorted = if options.minimize_tag_varints && chunks.iter().any(|ck| ck.tag_length(msg) != tag_length(ck.id)) {
+        options.sort_chunks(&mut chunks);
+        let mut sorted = Vec::with_capacity(msg.len());
+        for ck in &chunks {
+            sorted.extend_from_slice(&minimal_tag(ck));
+            sorted.extend_from_slice(&ck.bytes(msg)[ck.tag_length(msg)..]);
+        }
+        Cow::Owned(sorted)
+    } else if chunks
+        .windows(2)
+        .all(|w| options.sort_key(&w[0]) <= options.sort_key(&w[1]))
+    {
+        Cow::Borrowed(msg)
+    } else {
+        options.sort_chunks(&mut chunks);
+        Cow::Owned(assemble(&chunks, msg))
+    };
+    // Minimized tags may still leave the message too large
+    options.check_output_size(sorted.len())?;
+    Ok(sorted)
+}
+
+fn minimal_tag(ck: &Chunk) -> Vec<u8> {
+    let mut tag = Vec::with_capacity(5);
+    write_varint(u128::from(ck.id) << 3 | u128::from(ck.wire_type), &mut tag);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        has_reserved_field_numbers, is_extension_field, parse_usize, reserved_field_numbers,
+        sort_protobuf_message_with_options, SortError, SortOptions, DEFAULT_MAX_FIELDS,
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_parse_usize() {
+        assert_eq!(parse_usize("0"), 0);
+        assert_eq!(parse_usize("65536"), 65536);
+    }
+
+    #[test]
+    fn test_sort_with_max_fields() {
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02];
+        let sorted = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03];
+        assert_eq!(SortOptions::default().max_fields, DEFAULT_MAX_FIELDS);
+        let options = SortOptions::default().max_fields(3);
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            sorted.as_slice()
+        );
+        let options = SortOptions::default().max_fields(2);
+        assert!(sort_protobuf_message_with_options(&msg, &options).is_err());
+        assert!(sort_protobuf_message_with_options(&[], &options).is_ok());
+    }
+
+    #[test]
+    fn test_sort_with_minimal_tags() {
+        // Field 3, then field 1 with overlong 2-byte tag
+        let msg = [0x18, 0x03, 0x88, 0x00, 0x01];
+        let options = SortOptions::default();
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x88, 0x00, 0x01, 0x18, 0x03].as_slice()
+        );
+        let options = SortOptions::default().minimize_tag_varints(true);
+        let sorted = sort_protobuf_message_with_options(&msg, &options).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x18, 0x03].as_slice());
+        assert!(matches!(
+            sort_protobuf_message_with_options(&sorted, &options).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        // Already sorted, but with overlong tag
+        assert_eq!(
+            sort_protobuf_message_with_options(&[0x88, 0x80, 0x00, 0x01], &options).unwrap(),
+            [0x08, 0x01].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_is_extension_field() {
+        assert!(!is_extension_field(1));
+        assert!(!is_extension_field(999));
+        assert!(is_extension_field(1000));
+        assert!(is_extension_field((1 << 29) - 1));
+        assert!(!is_extension_field(1 << 29));
+    }
+
+    #[test]
+    fn test_sort_with_extensions_last() {
+        // Fields 101 (extension), 2, 1000, 100 (extension), 1
+        let msg = [
+            0xa8, 0x06, 0x01, 0x10, 0x02, 0xc0, 0x3e, 0x03, 0xa0, 0x06, 0x04, 0x08, 0x05,
+        ];
+        let options = SortOptions::default().extension_ranges([100..=199]);
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x08, 0x05, 0x10, 0x02, 0xa0, 0x06, 0x04, 0xa8, 0x06, 0x01, 0xc0, 0x3e, 0x03].as_slice()
+        );
+        let options = options.treat_extensions_as_unknown(true);
+        let sorted = sort_protobuf_message_with_options(&msg, &options).unwrap();
+        assert_eq!(
+            sorted,
+            [0x08, 0x05, 0x10, 0x02, 0xc0, 0x3e, 0x03, 0xa0, 0x06, 0x04, 0xa8, 0x06, 0x01].as_slice()
+        );
+        assert!(matches!(
+            sort_protobuf_message_with_options(&sorted, &options).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        let options = options.minimize_tag_varints(true);
+        assert_eq!(sort_protobuf_message_with_options(&msg, &options).unwrap(), sorted);
+    }
+
+    #[test]
+    fn test_sort_unstable() {
+        // Fields 2, 1, 3
+        let msg = [0x10, 0x02, 0x08, 0x01, 0x18, 0x03];
+        let options = SortOptions::default().stable(false);
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x18, 0x03].as_slice()
+        );
+        // Repeated field values stay together, in some order
+        let msg = [0x10, 0x02, 0x08, 0x01, 0x10, 0x03];
+        let sorted = sort_protobuf_message_with_options(&msg, &options).unwrap();
+        assert_eq!(sorted[..2], [0x08, 0x01]);
+        assert!(matches!(
+            sort_protobuf_message_with_options(&[0x08, 0x01, 0x08, 0x00], &options).unwrap(),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_reserved_field_numbers() {
+        // Fields 19999, 1, 19000, 19999
+        let msg = [
+            0xf8, 0xe1, 0x09, 0x01, 0x08, 0x01, 0xc0, 0xa3, 0x09, 0x02, 0xf8, 0xe1, 0x09, 0x03,
+        ];
+        assert!(has_reserved_field_numbers(&msg).unwrap());
+        assert_eq!(reserved_field_numbers(&msg).unwrap(), [19000, 19999]);
+        // Fields 18999 and 20000
+        let msg = [0xb8, 0xa3, 0x09, 0x01, 0x80, 0xe2, 0x09, 0x01];
+        assert!(!has_reserved_field_numbers(&msg).unwrap());
+        assert!(reserved_field_numbers(&msg).unwrap().is_empty());
+        assert!(has_reserved_field_numbers(&[0x0F]).is_err());
+        assert!(reserved_field_numbers(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_rejecting_reserved_field_numbers() {
+        // Fields 19000, 1
+        let msg = [0xc0, 0xa3, 0x09, 0x02, 0x08, 0x01];
+        let options = SortOptions::default();
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x08, 0x01, 0xc0, 0xa3, 0x09, 0x02].as_slice()
+        );
+        let options = options.reject_reserved_field_numbers(true);
+        assert!(sort_protobuf_message_with_options(&msg, &options).is_err());
+        assert!(sort_protobuf_message_with_options(&[0x10, 0x02, 0x08, 0x01], &options).is_ok());
+    }
+
+    #[test]
+    fn test_sort_with_max_output_bytes() {
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        let options = SortOptions::default().max_output_bytes(4);
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x08, 0x01, 0x10, 0x02].as_slice()
+        );
+        let options = options.max_output_bytes(3);
+        assert!(matches!(
+            sort_protobuf_message_with_options(&msg, &options),
+            Err(SortError::OutputTooLarge { size: 4, limit: 3 })
+        ));
+        // Field 1 with overlong tag fits once the tag is minimized
+        let msg = [0x88, 0x00, 0x01];
+        let options = options.max_output_bytes(2);
+        assert!(sort_protobuf_message_with_options(&msg, &options).is_err());
+        let options = options.minimize_tag_varints(true);
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x08, 0x01].as_slice()
+        );
+        // Already minimal tags don't skip the check
+        let options = options.max_output_bytes(3);
+        assert!(matches!(
+            sort_protobuf_message_with_options(&[0x10, 0x02, 0x08, 0x01], &options),
+            Err(SortError::OutputTooLarge { size: 4, limit: 3 })
+        ));
+        assert!(matches!(
+            sort_protobuf_message_with_options(&[0x0F], &options),
+            Err(SortError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_sort_with_custom_wire_type_handler() {
+        // Field 2 with wire type 6 and a 1-byte length prefix, field 1
+        let msg = [0x16, 0x02, 0xaa, 0xbb, 0x08, 0x01];
+        assert!(sort_protobuf_message_with_options(&msg, &SortOptions::default()).is_err());
+        let options = SortOptions::default().custom_wire_type_handler(|wire_type, bytes| match (wire_type, bytes) {
+            (6, [len, ..]) => Ok(1 + usize::from(*len)),
+            _ => Err(crate::ParseError),
+        });
+        assert_eq!(
+            sort_protobuf_message_with_options(&msg, &options).unwrap(),
+            [0x08, 0x01, 0x16, 0x02, 0xaa, 0xbb].as_slice()
+        );
+        for msg in [
+            // Wire type 7 is rejected by the handler
+            [0x17, 0x00].as_slice(),
+            // Length beyond the end of the message
+            &[0x16, 0x03, 0xaa, 0xbb],
+            // Field ID 0
+            &[0x06, 0x00],
+        ] {
+            assert!(
+                sort_protobuf_message_with_options(msg, &options).is_err(),
+                "{:02x?}",
+                msg
+            );
+        }
+        let options = options.max_fields(1);
+        assert!(sort_protobuf_message_with_options(&msg, &options).is_err());
+    }
+}
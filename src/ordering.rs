@@ -0,0 +1,352 @@
+//! Alternative (non-canonical) field orders.
+
+use crate::{assemble, parse_message, read_varint, reorder_chunks, Chunk, ParseError};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum SwapError {
+    #[error("field {0} not found in protobuf message")]
+    FieldNotFound(u32),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Sort fields in the given protobuf message alphabetically by their names.
+///
+/// Fields missing in `names` are placed after the named ones, ordered by field ID.
+pub fn sort_message_fields_by_name<'a>(
+    msg: &'a [u8],
+    names: &HashMap<u32, String>,
+) -> Result<Cow<'a, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(reorder_chunks(msg, chunks, |ck| {
+        let name = names.get(&ck.id);
+        (name.is_none(), name, ck.id)
+    }))
+}
+
+/// Sort fields in the given protobuf message by wire type and then by field ID.
+///
+/// Wire types go in the order: varint, 64-bit, 32-bit, length-delimited,
+/// so that fixed-width fields precede variable-length ones.
+pub fn sort_protobuf_message_by_wire_then_id(msg: &[u8]) -> Result<Cow<'_, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(reorder_chunks(msg, chunks, wire_then_id_key))
+}
+
+/// Checks if fields in the given protobuf message are ordered
+/// as [`sort_protobuf_message_by_wire_then_id`] does.
+pub fn is_sorted_by_wire_then_id(msg: &[u8]) -> Result<bool, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(chunks
+        .windows(2)
+        .all(|w| wire_then_id_key(&w[0]) <= wire_then_id_key(&w[1])))
+}
+
+fn wire_then_id_key(ck: &Chunk) -> (u8, u32) {
+    let rank = match ck.wire_type {
+        0 => 0,
+        1 => 1,
+        5 => 2,
+        _ => 3,
+    };
+    (rank, ck.id)
+}
+
+/// Sort fields in the given protobuf message by their encoded size (including the tag)
+/// and then by field ID, so that small header-like fields go first.
+pub fn sort_protobuf_message_by_size(msg: &[u8]) -> Result<Cow<'_, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(reorder_chunks(msg, chunks, size_then_id_key))
+}
+
+/// Checks if fields in the given protobuf message are ordered
+/// as [`sort_protobuf_message_by_size`] does.
+pub fn is_sorted_by_size(msg: &[u8]) -> Result<bool, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(chunks
+        .windows(2)
+        .all(|w| size_then_id_key(&w[0]) <= size_then_id_key(&w[1])))
+}
+
+fn size_then_id_key(ck: &Chunk) -> (usize, u32) {
+    (ck.length, ck.id)
+}
+
+/// Sort fields in the given protobuf message by field ID, except the fields with `excluded_ids`,
+/// which stay at their original positions among the sorted fields.
+pub fn sort_protobuf_message_excluding<'a>(msg: &'a [u8], excluded_ids: &[u32]) -> Result<Cow<'a, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    let is_excluded = |ck: &Chunk| excluded_ids.contains(&ck.id);
+    let mut movable: Vec<&Chunk> = chunks.iter().filter(|ck| !is_excluded(ck)).collect();
+    if movable.windows(2).all(|w| w[0].id <= w[1].id) {
+        return Ok(Cow::Borrowed(msg));
+    }
+    movable.sort_by_key(|ck| ck.id);
+    let mut movable = movable.into_iter();
+    let mut sorted = Vec::with_capacity(msg.len());
+    for ck in &chunks {
+        let ck = if is_excluded(ck) {
+            ck
+        } else {
+            movable.next().expect("same number of fields")
+        };
+        sorted.extend_from_slice(ck.bytes(msg));
+    }
+    Ok(Cow::Owned(sorted))
+}
+
+/// Sort fields in the given protobuf message by field ID, keeping the alternatives of each oneof
+/// (given as sets of field IDs) adjacent.
+///
+/// A oneof group is placed at the position of its minimum field ID.
+pub fn sort_protobuf_message_with_oneofs<'a>(msg: &'a [u8], oneofs: &[&[u32]]) -> Result<Cow<'a, [u8]>, ParseError> {
+    let mut group_ids = HashMap::new();
+    for oneof in oneofs {
+        if let Some(&min_id) = oneof.iter().min() {
+            for &id in oneof.iter() {
+                group_ids.insert(id, min_id);
+            }
+        }
+    }
+    let chunks = parse_message(msg)?;
+    Ok(reorder_chunks(msg, chunks, |ck| {
+        let id = ck.id;
+        (group_ids.get(&id).copied().unwrap_or(id), id)
+    }))
+}
+
+/// Sort fields in the given protobuf message by field ID, placing the fields with
+/// proto3 default values (varint 0, empty bytes, zero fixed-width) after all the others.
+pub fn sort_protobuf_message_defaults_last(msg: &[u8]) -> Result<Cow<'_, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    Ok(reorder_chunks(msg, chunks, |ck| (has_default_value(ck, msg), ck.id)))
+}
+
+fn has_default_value(ck: &Chunk, msg: &[u8]) -> bool {
+    let payload = ck.payload(msg);
+    match ck.wire_type {
+        0 => matches!(read_varint(payload), Ok((0, _))),
+        _ => payload.iter().all(|&b| b == 0),
+    }
+}
+
+/// Sort fields in the given protobuf message by field ID, ordering occurrences of the same field
+/// with `tie`, which receives their payloads (without the tag and the length prefix).
+///
+/// E.g. repeated varint fields can be sorted numerically by comparing decoded payloads.
+pub fn sort_protobuf_message_with_tie_breaker<F>(msg: &[u8], tie: F) -> Result<Cow<'_, [u8]>, ParseError>
+where
+    F: Fn(&[u8], &[u8]) -> Ordering,
+{
+    let mut chunks = parse_message(msg)?;
+    let compare = |a: &Chunk, b: &Chunk| a.id.cmp(&b.id).then_with(|| tie(a.payload(msg), b.payload(msg)));
+    if chunks.windows(2).all(|w| compare(&w[0], &w[1]) != Ordering::Greater) {
+        return Ok(Cow::Borrowed(msg));
+    }
+    chunks.sort_by(compare);
+    Ok(Cow::Owned(assemble(&chunks, msg)))
+}
+
+/// Exchange positions of two fields in the given protobuf message, e.g. to test how decoders
+/// handle out-of-order fields.
+///
+/// All occurrences of `id_a` are placed where the first occurrence of `id_b` was and vice versa.
+pub fn swap_fields(msg: &[u8], id_a: u32, id_b: u32) -> Result<Cow<'_, [u8]>, SwapError> {
+    let chunks = parse_message(msg)?;
+    let first_a = chunks.iter().position(|ck| ck.id == id_a);
+    let first_b = chunks.iter().position(|ck| ck.id == id_b);
+    let (first_a, first_b) = match (first_a, first_b) {
+        (Some(a), Some(b)) => (a, b),
+        (None, _) => return Err(SwapError::FieldNotFound(id_a)),
+        (_, None) => return Err(SwapError::FieldNotFound(id_b)),
+    };
+    if id_a == id_b {
+        return Ok(Cow::Borrowed(msg));
+    }
+    let mut order: Vec<&Chunk> = Vec::with_capacity(chunks.len());
+    for (i, ck) in chunks.iter().enumerate() {
+        if i == first_a {
+            order.extend(chunks.iter().filter(|ck| ck.id == id_b));
+        } else if i == first_b {
+            order.extend(chunks.iter().filter(|ck| ck.id == id_a));
+        } else if ck.id != id_a && ck.id != id_b {
+            order.push(ck);
+        }
+    }
+    let mut swapped = Vec::with_capacity(msg.len());
+    for ck in order {
+        swapped.extend_from_slice(ck.bytes(msg));
+    }
+    Ok(Cow::Owned(swapped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_sorted_by_size, is_sorted_by_wire_then_id, sort_message_fields_by_name, sort_protobuf_message_by_size,
+        sort_protobuf_message_by_wire_then_id, sort_protobuf_message_defaults_last, sort_protobuf_message_excluding,
+        sort_protobuf_message_with_oneofs, sort_protobuf_message_with_tie_breaker, swap_fields, SwapError,
+    };
+    use crate::read_varint;
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sort_message_fields_by_name() {
+        let names = HashMap::from([(1, "updated_at".to_owned()), (2, "created_at".to_owned())]);
+        // Fields 3, 1, 2, 1
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02, 0x08, 0x04];
+        let sorted = sort_message_fields_by_name(&msg, &names).unwrap();
+        assert_eq!(sorted, [0x10, 0x02, 0x08, 0x01, 0x08, 0x04, 0x18, 0x03].as_slice());
+        assert!(matches!(
+            sort_message_fields_by_name(&sorted, &names).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(sort_message_fields_by_name(&[0x0F], &names).is_err());
+    }
+
+    #[test]
+    fn test_sort_by_wire_then_id() {
+        // Fields 1 (length-delimited), 2 (32-bit), 3 (varint), 4 (64-bit), 5 (varint)
+        let msg = [
+            0x0a, 0x00, 0x15, 1, 2, 3, 4, 0x18, 0x03, 0x21, 1, 2, 3, 4, 5, 6, 7, 8, 0x28, 0x05,
+        ];
+        let expected = [
+            0x18, 0x03, 0x28, 0x05, 0x21, 1, 2, 3, 4, 5, 6, 7, 8, 0x15, 1, 2, 3, 4, 0x0a, 0x00,
+        ];
+        assert!(!is_sorted_by_wire_then_id(&msg).unwrap());
+        let sorted = sort_protobuf_message_by_wire_then_id(&msg).unwrap();
+        assert_eq!(sorted, expected.as_slice());
+        assert!(is_sorted_by_wire_then_id(&sorted).unwrap());
+        assert!(matches!(
+            sort_protobuf_message_by_wire_then_id(&sorted).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(is_sorted_by_wire_then_id(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_by_size() {
+        // Fields 1 (3 bytes), 2 (2 bytes), 3 (5 bytes), 4 (2 bytes)
+        let msg = [0x08, 0x96, 0x01, 0x10, 0x02, 0x1a, 0x03, b'a', b'b', b'c', 0x20, 0x04];
+        assert!(!is_sorted_by_size(&msg).unwrap());
+        let sorted = sort_protobuf_message_by_size(&msg).unwrap();
+        assert_eq!(
+            sorted,
+            [0x10, 0x02, 0x20, 0x04, 0x08, 0x96, 0x01, 0x1a, 0x03, b'a', b'b', b'c'].as_slice()
+        );
+        assert!(is_sorted_by_size(&sorted).unwrap());
+        assert!(matches!(
+            sort_protobuf_message_by_size(&sorted).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(is_sorted_by_size(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_excluding() {
+        // Fields 5, 2, 9, 4, 1
+        let msg = [0x28, 0x05, 0x10, 0x02, 0x48, 0x09, 0x20, 0x04, 0x08, 0x01];
+        let sorted = sort_protobuf_message_excluding(&msg, &[9]).unwrap();
+        assert_eq!(
+            sorted,
+            [0x08, 0x01, 0x10, 0x02, 0x48, 0x09, 0x20, 0x04, 0x28, 0x05].as_slice()
+        );
+        assert!(matches!(
+            sort_protobuf_message_excluding(&sorted, &[9]).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            sort_protobuf_message_excluding(&msg, &[1, 4, 5]).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            sort_protobuf_message_excluding(&msg, &[]).unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x20, 0x04, 0x28, 0x05, 0x48, 0x09].as_slice()
+        );
+        assert!(sort_protobuf_message_excluding(&[0x0F], &[]).is_err());
+    }
+
+    #[test]
+    fn test_sort_with_tie_breaker() {
+        let by_value = |a: &[u8], b: &[u8]| read_varint(a).unwrap().0.cmp(&read_varint(b).unwrap().0);
+        // Fields 2, 1 (150), 1 (3), 1 (150)
+        let msg = [0x10, 0x02, 0x08, 0x96, 0x01, 0x08, 0x03, 0x08, 0x96, 0x01];
+        let sorted = sort_protobuf_message_with_tie_breaker(&msg, by_value).unwrap();
+        assert_eq!(
+            sorted,
+            [0x08, 0x03, 0x08, 0x96, 0x01, 0x08, 0x96, 0x01, 0x10, 0x02].as_slice()
+        );
+        assert!(matches!(
+            sort_protobuf_message_with_tie_breaker(&sorted, by_value).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        let reversed = sort_protobuf_message_with_tie_breaker(&msg, |a, b| by_value(b, a)).unwrap();
+        assert_eq!(
+            reversed,
+            [0x08, 0x96, 0x01, 0x08, 0x96, 0x01, 0x08, 0x03, 0x10, 0x02].as_slice()
+        );
+        assert!(sort_protobuf_message_with_tie_breaker(&[0x0F], by_value).is_err());
+    }
+
+    #[test]
+    fn test_sort_with_oneofs() {
+        // Fields 5, 2, 4, 1, 3, with oneof {2, 5}
+        let msg = [0x28, 0x05, 0x10, 0x02, 0x20, 0x04, 0x08, 0x01, 0x18, 0x03];
+        let sorted = sort_protobuf_message_with_oneofs(&msg, &[&[5, 2]]).unwrap();
+        assert_eq!(
+            sorted,
+            [0x08, 0x01, 0x10, 0x02, 0x28, 0x05, 0x18, 0x03, 0x20, 0x04].as_slice()
+        );
+        assert!(matches!(
+            sort_protobuf_message_with_oneofs(&sorted, &[&[5, 2]]).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            sort_protobuf_message_with_oneofs(&msg, &[&[]]).unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x18, 0x03, 0x20, 0x04, 0x28, 0x05].as_slice()
+        );
+        assert!(sort_protobuf_message_with_oneofs(&[0x0F], &[]).is_err());
+    }
+
+    #[test]
+    fn test_sort_defaults_last() {
+        // Fields 4 (0), 3 (empty), 2 (32-bit zero), 1 (1), 5 (32-bit non-zero), 1 (0)
+        let msg = [
+            0x20, 0x00, 0x1a, 0x00, 0x15, 0, 0, 0, 0, 0x08, 0x01, 0x2d, 0, 0, 1, 0, 0x08, 0x00,
+        ];
+        let expected = [
+            0x08, 0x01, 0x2d, 0, 0, 1, 0, 0x08, 0x00, 0x15, 0, 0, 0, 0, 0x1a, 0x00, 0x20, 0x00,
+        ];
+        let sorted = sort_protobuf_message_defaults_last(&msg).unwrap();
+        assert_eq!(sorted, expected.as_slice());
+        assert!(matches!(
+            sort_protobuf_message_defaults_last(&sorted).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(sort_protobuf_message_defaults_last(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_swap_fields() {
+        // Fields 1, 2, 3, 2
+        let msg = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03, 0x10, 0x04];
+        assert_eq!(
+            swap_fields(&msg, 1, 3).unwrap(),
+            [0x18, 0x03, 0x10, 0x02, 0x08, 0x01, 0x10, 0x04].as_slice()
+        );
+        assert_eq!(
+            swap_fields(&msg, 2, 3).unwrap(),
+            [0x08, 0x01, 0x18, 0x03, 0x10, 0x02, 0x10, 0x04].as_slice()
+        );
+        assert_eq!(swap_fields(&msg, 3, 2).unwrap(), swap_fields(&msg, 2, 3).unwrap());
+        assert_eq!(swap_fields(&msg, 2, 2).unwrap(), msg.as_slice());
+        assert!(matches!(swap_fields(&msg, 1, 5), Err(SwapError::FieldNotFound(5))));
+        assert!(matches!(swap_fields(&msg, 6, 1), Err(SwapError::FieldNotFound(6))));
+        assert!(matches!(swap_fields(&[0x0F], 1, 2), Err(SwapError::Parse(_))));
+    }
+}
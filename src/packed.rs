@@ -0,0 +1,88 @@
+use crate::{parse_message, read_varint, write_varint, ParseError};
+use std::borrow::Cow;
+
+/// Sort values inside a packed repeated varint field (e.g. a set of enum values) numerically.
+///
+/// Every occurrence of the field is sorted on its own, the order of fields is left intact.
+/// Sorted values are re-encoded as minimal varints, so the field length prefix may change.
+/// Fails if the field is not length-delimited or its payload is not a sequence of varints.
+pub fn sort_packed_varint_field(msg: &[u8], field_id: u32) -> Result<Cow<'_, [u8]>, ParseError> {
+    let chunks = parse_message(msg)?;
+    let mut sorted: Option<Vec<u8>> = None;
+    let mut copied = 0;
+    for ck in chunks.iter().filter(|ck| ck.id == field_id) {
+        if ck.wire_type != 2 {
+            // Not a packed field
+            return Err(ParseError);
+        }
+        let mut values = read_packed_varints(ck.payload(msg))?;
+        if values.windows(2).all(|w| w[0] <= w[1]) {
+            continue;
+        }
+        values.sort_unstable();
+        let mut payload = Vec::with_capacity(ck.length);
+        for value in values {
+            write_varint(value, &mut payload);
+        }
+        let tag_length = ck.tag_length(msg);
+        let out = sorted.get_or_insert_with(|| Vec::with_capacity(msg.len()));
+        out.extend_from_slice(&msg[copied..ck.offset + tag_length]);
+        write_varint(payload.len() as u128, out);
+        out.extend_from_slice(&payload);
+        copied = ck.offset + ck.length;
+    }
+    match sorted {
+        Some(mut out) => {
+            out.extend_from_slice(&msg[copied..]);
+            Ok(Cow::Owned(out))
+        }
+        None => Ok(Cow::Borrowed(msg)),
+    }
+}
+
+fn read_packed_varints(mut payload: &[u8]) -> Result<Vec<u128>, ParseError> {
+    let mut values = Vec::new();
+    while !payload.is_empty() {
+        let (value, len) = read_varint(payload)?;
+        values.push(value);
+        payload = &payload[len..];
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_packed_varint_field;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_sort_packed_varint_field() {
+        let msg = [0x08, 0x05, 0x22, 0x04, 0x03, 0xac, 0x02, 0x01, 0x28, 0x01];
+        let sorted = sort_packed_varint_field(&msg, 4).unwrap();
+        assert_eq!(
+            sorted,
+            [0x08, 0x05, 0x22, 0x04, 0x01, 0x03, 0xac, 0x02, 0x28, 0x01].as_slice()
+        );
+        assert!(matches!(
+            sort_packed_varint_field(&sorted, 4).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(sort_packed_varint_field(&msg, 7).unwrap(), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_sort_packed_varint_field_length_change() {
+        // Overlong encoding of 1 followed by 0
+        let msg = [0x22, 0x03, 0x81, 0x00, 0x00, 0x22, 0x02, 0x02, 0x01];
+        let sorted = sort_packed_varint_field(&msg, 4).unwrap();
+        assert_eq!(sorted, [0x22, 0x02, 0x00, 0x01, 0x22, 0x02, 0x01, 0x02].as_slice());
+    }
+
+    #[test]
+    fn test_sort_packed_varint_field_errors() {
+        assert!(sort_packed_varint_field(&[0x20, 0x01], 4).is_err());
+        assert!(sort_packed_varint_field(&[0x08, 0x01, 0x25, 0, 0, 0, 0], 4).is_err());
+        // Truncated varint in the packed payload
+        assert!(sort_packed_varint_field(&[0x22, 0x02, 0x01, 0x80], 4).is_err());
+    }
+}
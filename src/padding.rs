@@ -0,0 +1,56 @@
+use crate::{sort_protobuf_message, ParseError};
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID)
+/// and append `pad_byte` until the length is a multiple of `block_size`.
+///
+/// # Panics
+///
+/// Panics if `block_size` is 0.
+pub fn sort_protobuf_message_padded(msg: &[u8], block_size: usize, pad_byte: u8) -> Result<Vec<u8>, ParseError> {
+    assert!(block_size != 0, "block size must be non-zero");
+    let mut padded = sort_protobuf_message(msg)?.into_owned();
+    let padding = (block_size - padded.len() % block_size) % block_size;
+    padded.resize(padded.len() + padding, pad_byte);
+    Ok(padded)
+}
+
+/// Remove up to `block_size - 1` trailing `pad_byte` bytes added by [`sort_protobuf_message_padded`].
+///
+/// Padding can't be told apart from the message bytes, so `pad_byte` must never end the message,
+/// e.g. 0x00 is ambiguous if the last field is a zero varint.
+pub fn strip_padding(padded: &[u8], block_size: usize, pad_byte: u8) -> &[u8] {
+    let max_padding = block_size.saturating_sub(1).min(padded.len());
+    let padding = padded
+        .iter()
+        .rev()
+        .take(max_padding)
+        .take_while(|&&b| b == pad_byte)
+        .count();
+    &padded[..padded.len() - padding]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_padded, strip_padding};
+
+    #[test]
+    fn test_sort_message_padded() {
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        let padded = sort_protobuf_message_padded(&msg, 8, 0xff).unwrap();
+        assert_eq!(padded, [0x08, 0x01, 0x10, 0x02, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(strip_padding(&padded, 8, 0xff), [0x08, 0x01, 0x10, 0x02]);
+        assert_eq!(sort_protobuf_message_padded(&msg, 4, 0xff).unwrap().len(), 4);
+        assert_eq!(sort_protobuf_message_padded(&msg, 3, 0xff).unwrap().len(), 6);
+        assert!(sort_protobuf_message_padded(&[], 16, 0xff).unwrap().is_empty());
+        assert!(sort_protobuf_message_padded(&[0x0F], 16, 0xff).is_err());
+        assert_eq!(strip_padding(&[0xff; 4], 4, 0xff), [0xff]);
+        assert_eq!(strip_padding(&[0x08, 0x01], 1, 0x01), [0x08, 0x01]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sort_message_padded_zero_block() {
+        let _ = sort_protobuf_message_padded(&[0x08, 0x01], 0, 0xff);
+    }
+}
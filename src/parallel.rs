@@ -0,0 +1,29 @@
+use crate::{into_canonical_bytes, ParseError};
+use rayon::prelude::*;
+
+/// Sort fields of each of the given protobuf messages in the "canonical" order (by field ID)
+/// on the `rayon` thread pool, reusing their buffers.
+///
+/// Results are in the same order as the input messages.
+pub fn sort_multiple_parallel(messages: Vec<Vec<u8>>) -> Vec<Result<Vec<u8>, ParseError>> {
+    messages.into_par_iter().map(into_canonical_bytes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_multiple_parallel;
+
+    #[test]
+    fn test_sort_multiple_parallel() {
+        let messages: Vec<Vec<u8>> = (0..100u8).map(|i| vec![0x10, i, 0x08, 0x01]).collect();
+        let sorted = sort_multiple_parallel(messages);
+        assert_eq!(sorted.len(), 100);
+        for (i, sorted) in sorted.into_iter().enumerate() {
+            assert_eq!(sorted.unwrap(), [0x08, 0x01, 0x10, i as u8]);
+        }
+        let sorted = sort_multiple_parallel(vec![vec![0x0F], vec![]]);
+        assert!(sorted[0].is_err());
+        assert!(sorted[1].as_ref().unwrap().is_empty());
+        assert!(sort_multiple_parallel(Vec::new()).is_empty());
+    }
+}
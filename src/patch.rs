@@ -0,0 +1,70 @@
+use crate::{parse_fields, EncodeError, MessageBuilder, ParseError, WireType};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum PatchError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// Update of a protobuf message fields, see [`apply_patch`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessagePatch {
+    /// New fields as raw payloads (without the tag and the length prefix), replacing all the
+    /// existing occurrences of the same field IDs. Repeated IDs add several occurrences.
+    pub set_fields: Vec<(u32, WireType, Vec<u8>)>,
+    pub remove_field_ids: Vec<u32>,
+}
+
+/// Apply the patch to the given protobuf message: remove the fields listed in
+/// `remove_field_ids` and `set_fields`, then add `set_fields`. The result is sorted by field ID.
+pub fn apply_patch(msg: &[u8], patch: &MessagePatch) -> Result<Vec<u8>, PatchError> {
+    let replaced = |field_id| {
+        patch.remove_field_ids.contains(&field_id) || patch.set_fields.iter().any(|(id, _, _)| *id == field_id)
+    };
+    let mut builder = MessageBuilder::new();
+    for field in parse_fields(msg)? {
+        if !replaced(field.id()) {
+            builder.push_field(field.id(), field.wire_type(), field.payload());
+        }
+    }
+    for (field_id, wire_type, payload) in &patch.set_fields {
+        builder.push_field(*field_id, *wire_type, payload);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_patch, MessagePatch, PatchError};
+    use crate::WireType;
+
+    #[test]
+    fn test_apply_patch() {
+        // Fields 3, 1, 3, 2
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x18, 0x04, 0x10, 0x02];
+        let patch = MessagePatch {
+            set_fields: vec![
+                (3, WireType::Varint, vec![0x05]),
+                (4, WireType::LengthDelimited, b"a".to_vec()),
+            ],
+            remove_field_ids: vec![2, 7],
+        };
+        assert_eq!(
+            apply_patch(&msg, &patch).unwrap(),
+            [0x08, 0x01, 0x18, 0x05, 0x22, 0x01, b'a']
+        );
+        assert_eq!(
+            apply_patch(&msg, &MessagePatch::default()).unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x18, 0x03, 0x18, 0x04]
+        );
+        assert!(matches!(apply_patch(&[0x0F], &patch), Err(PatchError::Parse(_))));
+        let patch = MessagePatch {
+            set_fields: vec![(0, WireType::Varint, vec![0x01])],
+            remove_field_ids: vec![],
+        };
+        assert!(matches!(apply_patch(&msg, &patch), Err(PatchError::Encode(_))));
+    }
+}
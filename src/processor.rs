@@ -0,0 +1,103 @@
+use crate::{parse_fields, Field, ParseError, WireType};
+use std::collections::HashMap;
+use std::fmt;
+
+type FieldHandler = Box<dyn Fn(u32, WireType, &[u8]) + Send + Sync>;
+
+/// Calls registered handlers for the fields of protobuf messages in the "canonical" order
+/// (by field ID), without decoding the messages.
+///
+/// Handlers get the field ID, the wire type and the payload without the tag and the length prefix.
+#[derive(Default)]
+pub struct MessageProcessor {
+    field_handlers: HashMap<u32, Vec<FieldHandler>>,
+    any_field_handlers: Vec<FieldHandler>,
+}
+
+impl MessageProcessor {
+    pub fn new() -> Self {
+        MessageProcessor::default()
+    }
+
+    /// Register a handler for the fields with the given ID.
+    pub fn on_field<F>(&mut self, field_id: u32, handler: F)
+    where
+        F: Fn(u32, WireType, &[u8]) + Send + Sync + 'static,
+    {
+        self.field_handlers.entry(field_id).or_default().push(Box::new(handler));
+    }
+
+    /// Register a handler for all the fields, called after the handlers of the specific field.
+    pub fn on_any_field<F>(&mut self, handler: F)
+    where
+        F: Fn(u32, WireType, &[u8]) + Send + Sync + 'static,
+    {
+        self.any_field_handlers.push(Box::new(handler));
+    }
+
+    /// Call the handlers for each field of the given message, sorted by field ID. Repeated fields
+    /// keep their relative order. No handlers are called if the message is malformed.
+    pub fn process_sorted(&self, msg: &[u8]) -> Result<(), ParseError> {
+        let mut fields = parse_fields(msg)?;
+        fields.sort_by_key(Field::id);
+        for field in &fields {
+            let handlers = self.field_handlers.get(&field.id()).into_iter().flatten();
+            for handler in handlers.chain(&self.any_field_handlers) {
+                handler(field.id(), field.wire_type(), field.payload());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for MessageProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut field_ids: Vec<u32> = self.field_handlers.keys().copied().collect();
+        field_ids.sort_unstable();
+        f.debug_struct("MessageProcessor")
+            .field("field_ids", &field_ids)
+            .field("any_field_handlers", &self.any_field_handlers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageProcessor;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_message_processor() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut processor = MessageProcessor::new();
+        let log = events.clone();
+        processor.on_field(2, move |id, wire_type, payload| {
+            log.lock()
+                .unwrap()
+                .push(format!("field {}: {:?} {:02x?}", id, wire_type, payload))
+        });
+        let log = events.clone();
+        processor.on_any_field(move |id, _, _| log.lock().unwrap().push(format!("any {}", id)));
+        // Fields 2 (length-delimited), 1, 2
+        processor
+            .process_sorted(&[0x12, 0x01, b'a', 0x08, 0x01, 0x10, 0x05])
+            .unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "any 1",
+                "field 2: LengthDelimited [61]",
+                "any 2",
+                "field 2: Varint [05]",
+                "any 2"
+            ]
+        );
+        events.lock().unwrap().clear();
+        assert!(processor.process_sorted(&[0x08, 0x01, 0x0F]).is_err());
+        assert!(events.lock().unwrap().is_empty());
+        assert_eq!(
+            format!("{:?}", processor),
+            "MessageProcessor { field_ids: [2], any_field_handlers: 1 }"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{assemble, do_sort, is_sorted, parse_message, sort_chunks, ParseError};
+use std::borrow::Cow;
+use std::time::Instant;
+
+/// Stage of [`sort_protobuf_message_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortPhase {
+    Parsing,
+    Sorting,
+    Assembling,
+}
+
+/// Progress report at the start of a sort phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortProgress {
+    pub phase: SortPhase,
+    /// Bytes of the message handled by the previous phases
+    pub bytes_processed: usize,
+    pub total_bytes: usize,
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// calling `progress` at the start of each phase, e.g. to drive a progress bar.
+///
+/// Already sorted message ends after the parsing phase.
+pub fn sort_protobuf_message_with_progress<F: Fn(SortProgress)>(
+    msg: &[u8],
+    progress: F,
+) -> Result<Cow<'_, [u8]>, ParseError> {
+    let report = |phase, bytes_processed| {
+        progress(SortProgress {
+            phase,
+            bytes_processed,
+            total_bytes: msg.len(),
+        })
+    };
+    report(SortPhase::Parsing, 0);
+    let mut chunks = parse_message(msg)?;
+    if is_sorted(&chunks) {
+        return Ok(Cow::Borrowed(msg));
+    }
+    report(SortPhase::Sorting, msg.len());
+    sort_chunks(&mut chunks);
+    report(SortPhase::Assembling, msg.len());
+    Ok(Cow::Owned(assemble(&chunks, msg)))
+}
+
+/// Statistics of a single [`sort_protobuf_message_instrumented`] call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortMetrics {
+    pub field_count: usize,
+    pub unique_field_ids: usize,
+    pub was_already_sorted: bool,
+    pub input_bytes: usize,
+    pub parse_ns: u64,
+    /// Time of sorting and assembling the message, 0 if it was already sorted
+    pub sort_ns: u64,
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// calling `on_complete` with the sort statistics, e.g. for profiling.
+///
+/// `on_complete` is not called if the message is malformed.
+pub fn sort_protobuf_message_instrumented<F: Fn(SortMetrics)>(
+    msg: &[u8],
+    on_complete: F,
+) -> Result<Cow<'_, [u8]>, ParseError> {
+    let elapsed_ns = |start: Instant| u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+    let start = Instant::now();
+    let mut chunks = parse_message(msg)?;
+    let parse_ns = elapsed_ns(start);
+    let was_already_sorted = is_sorted(&chunks);
+    let (sorted, sort_ns) = if was_already_sorted {
+        (Cow::Borrowed(msg), 0)
+    } else {
+        let start = Instant::now();
+        (Cow::Owned(do_sort(&mut chunks, msg)), elapsed_ns(start))
+    };
+    // Chunks are sorted at this point, so repeated fields are adjacent
+    let repeated = chunks.windows(2).filter(|w| w[0].id == w[1].id).count();
+    on_complete(SortMetrics {
+        field_count: chunks.len(),
+        unique_field_ids: chunks.len() - repeated,
+        was_already_sorted,
+        input_bytes: msg.len(),
+        parse_ns,
+        sort_ns,
+    });
+    Ok(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_instrumented, sort_protobuf_message_with_progress, SortPhase, SortProgress};
+    use std::cell::{Cell, RefCell};
+
+    #[test]
+    fn test_sort_message_with_progress() {
+        let phases = RefCell::new(Vec::new());
+        let progress = |p: SortProgress| {
+            assert_eq!(p.total_bytes, 4);
+            phases.borrow_mut().push((p.phase, p.bytes_processed));
+        };
+        // Fields 2, 1
+        let sorted = sort_protobuf_message_with_progress(&[0x10, 0x02, 0x08, 0x01], progress).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x10, 0x02].as_slice());
+        assert_eq!(
+            phases.take(),
+            [
+                (SortPhase::Parsing, 0),
+                (SortPhase::Sorting, 4),
+                (SortPhase::Assembling, 4)
+            ]
+        );
+        sort_protobuf_message_with_progress(&sorted, progress).unwrap();
+        assert_eq!(phases.take(), [(SortPhase::Parsing, 0)]);
+        assert!(sort_protobuf_message_with_progress(&[0x0F, 0, 0, 0], progress).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_instrumented() {
+        let metrics = Cell::new(None);
+        let on_complete = |m| metrics.set(Some(m));
+        // Fields 2, 1, 2
+        let sorted = sort_protobuf_message_instrumented(&[0x10, 0x02, 0x08, 0x01, 0x10, 0x03], on_complete).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x10, 0x02, 0x10, 0x03].as_slice());
+        let m = metrics.take().unwrap();
+        assert_eq!((m.field_count, m.unique_field_ids, m.input_bytes), (3, 2, 6));
+        assert!(!m.was_already_sorted);
+        sort_protobuf_message_instrumented(&sorted, on_complete).unwrap();
+        let m = metrics.take().unwrap();
+        assert!(m.was_already_sorted);
+        assert_eq!((m.unique_field_ids, m.sort_ns), (2, 0));
+        sort_protobuf_message_instrumented(&[], on_complete).unwrap();
+        assert_eq!(metrics.take().unwrap().unique_field_ids, 0);
+        assert!(sort_protobuf_message_instrumented(&[0x0F], on_complete).is_err());
+        assert!(metrics.take().is_none());
+    }
+}
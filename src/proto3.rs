@@ -0,0 +1,152 @@
+use crate::{parse_message, ParseError};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Whether a field may appear in a message once or many times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    Singular,
+    Repeated,
+}
+
+/// Kind of the values of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    /// Numeric, `bool` or `enum` value, repeated ones must be packed
+    Scalar,
+    /// `string` or `bytes` value
+    Bytes,
+    /// Embedded message
+    Message,
+}
+
+/// Minimal description of a proto3 message type for [`is_proto3_compliant`].
+#[derive(Debug, Clone, Default)]
+pub struct Proto3Schema {
+    fields: HashMap<u32, (Cardinality, ValueKind)>,
+}
+
+impl Proto3Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, field_id: u32, cardinality: Cardinality, kind: ValueKind) -> Self {
+        self.fields.insert(field_id, (cardinality, kind));
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Proto3Violation {
+    #[error("singular field {field_id} occurs {count} times")]
+    SingularFieldRepeated { field_id: u32, count: usize },
+    #[error("repeated scalar field {field_id} is not packed")]
+    NotPacked { field_id: u32 },
+    #[error("field {field_id} has unexpected wire type {wire_type}")]
+    WrongWireType { field_id: u32, wire_type: u8 },
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum Proto3ComplianceError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("protobuf message violates proto3 encoding rules: {0:?}")]
+    Violations(Vec<Proto3Violation>),
+}
+
+/// Checks the given protobuf message against proto3 encoding rules for the given schema:
+/// singular fields occur at most once and repeated scalar fields are packed.
+///
+/// Fails with all the found violations. Returns `false` if the message has fields
+/// missing in the schema, which can't be checked.
+pub fn is_proto3_compliant(msg: &[u8], schema: &Proto3Schema) -> Result<bool, Proto3ComplianceError> {
+    let chunks = parse_message(msg)?;
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    let mut violations = Vec::new();
+    let mut all_known = true;
+    for ck in &chunks {
+        let field_id = ck.id;
+        let Some(&(cardinality, kind)) = schema.fields.get(&field_id) else {
+            all_known = false;
+            continue;
+        };
+        let count = counts.entry(field_id).or_default();
+        *count += 1;
+        if cardinality == Cardinality::Singular && *count == 2 {
+            // The total count is known only after all the fields are seen
+            violations.push(Proto3Violation::SingularFieldRepeated { field_id, count: 0 });
+        }
+        match (cardinality, kind, ck.wire_type) {
+            (_, ValueKind::Bytes | ValueKind::Message, 2) | (Cardinality::Repeated, ValueKind::Scalar, 2) => {}
+            (Cardinality::Singular, ValueKind::Scalar, 0 | 1 | 5) => {}
+            (Cardinality::Repeated, ValueKind::Scalar, 0 | 1 | 5) => {
+                let violation = Proto3Violation::NotPacked { field_id };
+                if !violations.contains(&violation) {
+                    violations.push(violation);
+                }
+            }
+            (_, _, wire_type) => violations.push(Proto3Violation::WrongWireType { field_id, wire_type }),
+        }
+    }
+    for violation in &mut violations {
+        if let Proto3Violation::SingularFieldRepeated { field_id, count } = violation {
+            *count = counts[field_id];
+        }
+    }
+    if violations.is_empty() {
+        Ok(all_known)
+    } else {
+        Err(Proto3ComplianceError::Violations(violations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_proto3_compliant, Cardinality, Proto3ComplianceError, Proto3Schema, Proto3Violation, ValueKind};
+
+    #[test]
+    fn test_is_proto3_compliant() {
+        let schema = Proto3Schema::new()
+            .field(1, Cardinality::Singular, ValueKind::Scalar)
+            .field(2, Cardinality::Repeated, ValueKind::Scalar)
+            .field(3, Cardinality::Repeated, ValueKind::Bytes)
+            .field(4, Cardinality::Singular, ValueKind::Message);
+        // 1: 150, 2: packed [1, 2], 3: "a", 3: "b", 4: {}
+        let msg = [
+            0x08, 0x96, 0x01, 0x12, 0x02, 0x01, 0x02, 0x1a, 0x01, b'a', 0x1a, 0x01, b'b', 0x22, 0x00,
+        ];
+        assert!(is_proto3_compliant(&msg, &schema).unwrap());
+        assert!(is_proto3_compliant(&[], &schema).unwrap());
+        // Unknown field 5
+        assert!(!is_proto3_compliant(&[0x28, 0x01], &schema).unwrap());
+        assert!(matches!(
+            is_proto3_compliant(&[0x0F], &schema),
+            Err(Proto3ComplianceError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_proto3_violations() {
+        let schema = Proto3Schema::new()
+            .field(1, Cardinality::Singular, ValueKind::Scalar)
+            .field(2, Cardinality::Repeated, ValueKind::Scalar)
+            .field(4, Cardinality::Singular, ValueKind::Message);
+        // 1: 1, 2: 1, 1: 2, 2: 2, 4: 3, 1: 3
+        let msg = [0x08, 0x01, 0x10, 0x01, 0x08, 0x02, 0x10, 0x02, 0x20, 0x03, 0x08, 0x03];
+        let Err(Proto3ComplianceError::Violations(violations)) = is_proto3_compliant(&msg, &schema) else {
+            panic!("violations expected");
+        };
+        assert_eq!(
+            violations,
+            [
+                Proto3Violation::NotPacked { field_id: 2 },
+                Proto3Violation::SingularFieldRepeated { field_id: 1, count: 3 },
+                Proto3Violation::WrongWireType {
+                    field_id: 4,
+                    wire_type: 0
+                },
+            ]
+        );
+    }
+}
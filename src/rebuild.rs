@@ -0,0 +1,87 @@
+use crate::{parse_fields, MessageBuilder, ParseError, WireType};
+use std::collections::{BTreeSet, HashMap};
+
+/// Edits fields of an existing protobuf message: removes some of their occurrences and adds
+/// new fields, see [`MessageRebuilder::rebuild_sorted`].
+#[derive(Debug, Clone)]
+pub struct MessageRebuilder<'a> {
+    original: &'a [u8],
+    additions: Vec<(u32, WireType, Vec<u8>)>,
+    /// (occurrence index, field ID) pairs
+    removals: BTreeSet<(usize, u32)>,
+}
+
+impl<'a> MessageRebuilder<'a> {
+    pub fn new(original: &'a [u8]) -> Self {
+        MessageRebuilder {
+            original,
+            additions: Vec::new(),
+            removals: BTreeSet::new(),
+        }
+    }
+
+    /// Add a field with the given raw payload, i.e. without the tag and the length prefix.
+    /// It goes after the original occurrences of the same field.
+    pub fn add_field(&mut self, field_id: u32, wire_type: WireType, payload: &[u8]) -> &mut Self {
+        self.additions.push((field_id, wire_type, payload.to_vec()));
+        self
+    }
+
+    /// Remove the `index`-th (0-based) occurrence of the field in the original message,
+    /// nothing is removed if there's no such occurrence.
+    pub fn remove_field_at_index(&mut self, field_id: u32, index: usize) -> &mut Self {
+        self.removals.insert((index, field_id));
+        self
+    }
+
+    /// Apply the edits to the original message, the result is sorted by field ID.
+    ///
+    /// Fails if the original message is malformed or an added payload doesn't match its wire type.
+    pub fn rebuild_sorted(self) -> Result<Vec<u8>, ParseError> {
+        let mut builder = MessageBuilder::new();
+        let mut occurrences: HashMap<u32, usize> = HashMap::new();
+        for field in parse_fields(self.original)? {
+            let index = occurrences.entry(field.id()).or_default();
+            let removed = self.removals.contains(&(*index, field.id()));
+            *index += 1;
+            if !removed {
+                builder.push_field(field.id(), field.wire_type(), field.payload());
+            }
+        }
+        for (field_id, wire_type, payload) in &self.additions {
+            builder.push_field(*field_id, *wire_type, payload);
+        }
+        builder.build().map_err(|_| ParseError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MessageRebuilder;
+    use crate::WireType;
+
+    #[test]
+    fn test_rebuild_message() {
+        // Fields 3, 1, 3, 2
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x18, 0x04, 0x10, 0x02];
+        let mut rebuilder = MessageRebuilder::new(&msg);
+        rebuilder
+            .remove_field_at_index(3, 0)
+            .remove_field_at_index(2, 0)
+            .remove_field_at_index(1, 5)
+            .add_field(3, WireType::Varint, &[0x05])
+            .add_field(4, WireType::LengthDelimited, b"a");
+        assert_eq!(
+            rebuilder.rebuild_sorted().unwrap(),
+            [0x08, 0x01, 0x18, 0x04, 0x18, 0x05, 0x22, 0x01, b'a']
+        );
+        assert_eq!(
+            MessageRebuilder::new(&msg).rebuild_sorted().unwrap(),
+            [0x08, 0x01, 0x10, 0x02, 0x18, 0x03, 0x18, 0x04]
+        );
+        assert!(MessageRebuilder::new(&[0x0F]).rebuild_sorted().is_err());
+        let mut rebuilder = MessageRebuilder::new(&msg);
+        rebuilder.add_field(5, WireType::Fixed32, &[0x01]);
+        assert!(rebuilder.rebuild_sorted().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{do_sort, is_sorted, parse_message, ParseError};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Field that changes its position when the message is sorted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMove {
+    pub field_id: u32,
+    /// 0-based index of the field in the original message
+    pub original_position: usize,
+    /// 0-based index of the field in the sorted message
+    pub sorted_position: usize,
+}
+
+/// How sorting in the "canonical" order (by field ID) changes the message,
+/// see [`sort_message_report_changes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortReport {
+    pub was_sorted: bool,
+    pub field_count: usize,
+    /// Moved fields, in the sorted order
+    pub fields_moved: Vec<FieldMove>,
+}
+
+/// Report which fields of the given protobuf message would move if it was sorted,
+/// without sorting the message itself.
+pub fn sort_message_report_changes(msg: &[u8]) -> Result<SortReport, ParseError> {
+    let chunks = parse_message(msg)?;
+    let was_sorted = is_sorted(&chunks);
+    let mut order: Vec<usize> = (0..chunks.len()).collect();
+    order.sort_by_key(|&i| chunks[i].id);
+    let mut fields_moved = Vec::new();
+    for (sorted_position, original_position) in order.into_iter().enumerate() {
+        if sorted_position != original_position {
+            fields_moved.push(FieldMove {
+                field_id: chunks[original_position].id,
+                original_position,
+                sorted_position,
+            });
+        }
+    }
+    Ok(SortReport {
+        was_sorted,
+        field_count: chunks.len(),
+        fields_moved,
+    })
+}
+
+/// Position of a single field before and after sorting, see [`SortLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortLogEntry {
+    pub field_id: u32,
+    pub wire_type: u8,
+    pub original_position: usize,
+    pub sorted_position: usize,
+}
+
+/// Positions of all the fields of a message sorted by [`sort_protobuf_message_verbose`],
+/// in the original order. `Display` shows one field per line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SortLog {
+    pub entries: Vec<SortLogEntry>,
+}
+
+impl SortLog {
+    /// Machine-readable form of the log, e.g.:
+    /// `[{"field_id":1,"wire_type":0,"original_position":0,"sorted_position":0}]`.
+    pub fn to_json(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    r#"{{"field_id":{},"wire_type":{},"original_position":{},"sorted_position":{}}}"#,
+                    entry.field_id, entry.wire_type, entry.original_position, entry.sorted_position
+                )
+            })
+            .collect();
+        format!("[{}]", entries.join(","))
+    }
+}
+
+impl fmt::Display for SortLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            write!(f, "Field {} (wire:{}) ", entry.field_id, entry.wire_type)?;
+            if entry.original_position == entry.sorted_position {
+                writeln!(f, "stayed at position {}", entry.original_position)?;
+            } else {
+                writeln!(
+                    f,
+                    "moved from position {} to position {}",
+                    entry.original_position, entry.sorted_position
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// also returning the log of field movements, e.g. to debug unexpected hash changes.
+pub fn sort_protobuf_message_verbose(msg: &[u8]) -> Result<(Cow<'_, [u8]>, SortLog), ParseError> {
+    let mut chunks = parse_message(msg)?;
+    let mut order: Vec<usize> = (0..chunks.len()).collect();
+    order.sort_by_key(|&i| chunks[i].id);
+    let mut entries: Vec<SortLogEntry> = chunks
+        .iter()
+        .enumerate()
+        .map(|(position, ck)| SortLogEntry {
+            field_id: ck.id,
+            wire_type: ck.wire_type,
+            original_position: position,
+            sorted_position: position,
+        })
+        .collect();
+    for (sorted_position, &original_position) in order.iter().enumerate() {
+        entries[original_position].sorted_position = sorted_position;
+    }
+    let sorted = if is_sorted(&chunks) {
+        Cow::Borrowed(msg)
+    } else {
+        Cow::Owned(do_sort(&mut chunks, msg))
+    };
+    Ok((sorted, SortLog { entries }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_message_report_changes, sort_protobuf_message_verbose, FieldMove};
+
+    #[test]
+    fn test_sort_message_report_changes() {
+        // Fields 3, 1, 2, 4
+        let msg = [0x18, 0x03, 0x08, 0x01, 0x10, 0x02, 0x20, 0x04];
+        let report = sort_message_report_changes(&msg).unwrap();
+        assert!(!report.was_sorted);
+        assert_eq!(report.field_count, 4);
+        let moved = |field_id, original_position, sorted_position| FieldMove {
+            field_id,
+            original_position,
+            sorted_position,
+        };
+        assert_eq!(report.fields_moved, [moved(1, 1, 0), moved(2, 2, 1), moved(3, 0, 2)]);
+        let report = sort_message_report_changes(&[0x08, 0x01, 0x08, 0x02]).unwrap();
+        assert!(report.was_sorted);
+        assert_eq!(report.field_count, 2);
+        assert!(report.fields_moved.is_empty());
+        assert!(sort_message_report_changes(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_sort_message_verbose() {
+        // Fields 13, 1, 2 (length-delimited)
+        let msg = [0x68, 0x01, 0x08, 0x02, 0x12, 0x00];
+        let (sorted, log) = sort_protobuf_message_verbose(&msg).unwrap();
+        assert_eq!(sorted, [0x08, 0x02, 0x12, 0x00, 0x68, 0x01].as_slice());
+        assert_eq!(
+            log.to_string(),
+            "Field 13 (wire:0) moved from position 0 to position 2\n\
+             Field 1 (wire:0) moved from position 1 to position 0\n\
+             Field 2 (wire:2) moved from position 2 to position 1\n"
+        );
+        let (sorted, log) = sort_protobuf_message_verbose(&[0x08, 0x01]).unwrap();
+        assert!(matches!(sorted, std::borrow::Cow::Borrowed(_)));
+        assert_eq!(log.to_string(), "Field 1 (wire:0) stayed at position 0\n");
+        assert_eq!(
+            log.to_json(),
+            r#"[{"field_id":1,"wire_type":0,"original_position":0,"sorted_position":0}]"#
+        );
+        assert_eq!(sort_protobuf_message_verbose(&[]).unwrap().1.to_json(), "[]");
+        assert!(sort_protobuf_message_verbose(&[0x0F]).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use crate::encode::{encode_tag, EncodeError};
+use crate::{parse_message, ParseError};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum RewriteError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// Changes field IDs in protobuf messages, e.g. for field number migrations.
+#[derive(Debug, Clone, Default)]
+pub struct MessageRewriter {
+    id_map: HashMap<u32, u32>,
+}
+
+impl MessageRewriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rewrite every occurrence of field `from` to field `to`.
+    pub fn remap_field_id(&mut self, from: u32, to: u32) {
+        self.id_map.insert(from, to);
+    }
+
+    /// Rewrite field IDs in the given message, the result is sorted by (new) field ID.
+    pub fn apply(&self, msg: &[u8]) -> Result<Vec<u8>, RewriteError> {
+        let chunks = parse_message(msg)?;
+        let mut fields = Vec::with_capacity(chunks.len());
+        for ck in &chunks {
+            let mapped = self.id_map.get(&ck.id);
+            match mapped {
+                Some(&new_id) => {
+                    let mut tag = Vec::new();
+                    encode_tag(new_id, ck.wire_type, &mut tag)?;
+                    let rest = &ck.bytes(msg)[ck.tag_length(msg)..];
+                    fields.push((new_id, Some(tag), rest));
+                }
+                None => fields.push((ck.id, None, ck.bytes(msg))),
+            }
+        }
+        fields.sort_by_key(|(id, _, _)| *id);
+        let mut result = Vec::with_capacity(msg.len());
+        for (_, tag, rest) in fields {
+            if let Some(tag) = tag {
+                result.extend_from_slice(&tag);
+            }
+            result.extend_from_slice(rest);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageRewriter, RewriteError};
+    use crate::EncodeError;
+
+    #[test]
+    fn test_rewrite_field_ids() {
+        let mut rewriter = MessageRewriter::new();
+        rewriter.remap_field_id(1, 20);
+        rewriter.remap_field_id(3, 2);
+        // Fields 1, 1, 3 (length-delimited), 4
+        let msg = [0x08, 0x01, 0x08, 0x02, 0x1a, 0x01, 0xff, 0x20, 0x04];
+        let rewritten = rewriter.apply(&msg).unwrap();
+        assert_eq!(
+            rewritten,
+            [0x12, 0x01, 0xff, 0x20, 0x04, 0xa0, 0x01, 0x01, 0xa0, 0x01, 0x02]
+        );
+        assert!(MessageRewriter::new().apply(&msg).unwrap() == msg);
+        assert!(matches!(rewriter.apply(&[0x0F]), Err(RewriteError::Parse(_))));
+        rewriter.remap_field_id(4, 0);
+        assert!(matches!(
+            rewriter.apply(&msg),
+            Err(RewriteError::Encode(EncodeError::InvalidFieldId(0)))
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{parse_field_header, sort_chunks, Chunk, ParseError, MAX_VARINT_LENGTH};
+
+/// Sort fields in the protobuf message stored in a ring buffer as two slices, `head` followed
+/// by `tail`, in the "canonical" order (by field ID).
+///
+/// Fields may span the boundary between the slices. The message is parsed in place,
+/// and the fields are copied directly to the sorted result.
+pub fn sort_protobuf_message_ring(head: &[u8], tail: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let length = head.len() + tail.len();
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while offset < length {
+        let chunk = parse_ring_field(head, tail, offset)?;
+        offset += chunk.length;
+        chunks.push(chunk);
+    }
+    sort_chunks(&mut chunks);
+    let mut sorted = Vec::with_capacity(length);
+    for ck in &chunks {
+        copy_range(head, tail, ck.offset, ck.length, &mut sorted);
+    }
+    Ok(sorted)
+}
+
+/// Parse the field at `offset` of the logical message `head` + `tail`
+fn parse_ring_field(head: &[u8], tail: &[u8], offset: usize) -> Result<Chunk, ParseError> {
+    // The tag and the varint value or length prefix that follows it are enough to find
+    // the field length, so only their bytes are copied
+    let mut header = [0; 2 * MAX_VARINT_LENGTH];
+    let remaining = head.len() + tail.len() - offset;
+    let header_length = remaining.min(header.len());
+    for (i, byte) in header[..header_length].iter_mut().enumerate() {
+        let position = offset + i;
+        *byte = if position < head.len() {
+            head[position]
+        } else {
+            tail[position - head.len()]
+        };
+    }
+    let mut chunk = parse_field_header(&header[..header_length], 0, None)?;
+    if chunk.length > remaining {
+        // Field length points beyond the end of the message
+        return Err(ParseError);
+    }
+    chunk.offset = offset;
+    Ok(chunk)
+}
+
+/// Append `length` bytes starting at `offset` of the logical message `head` + `tail`
+fn copy_range(head: &[u8], tail: &[u8], offset: usize, length: usize, buf: &mut Vec<u8>) {
+    let end = offset + length;
+    if offset < head.len() {
+        buf.extend_from_slice(&head[offset..end.min(head.len())]);
+    }
+    if end > head.len() {
+        buf.extend_from_slice(&tail[offset.saturating_sub(head.len())..end - head.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sort_protobuf_message_ring;
+
+    #[test]
+    fn test_sort_message_ring() {
+        // Fields 3 (length-delimited), 1 (multi-byte varint), 2 (fixed32)
+        let msg = [0x1a, 0x02, b'a', b'b', 0x08, 0x96, 0x01, 0x15, 1, 2, 3, 4];
+        let expected = [0x08, 0x96, 0x01, 0x15, 1, 2, 3, 4, 0x1a, 0x02, b'a', b'b'];
+        // Split at every position, including inside tags, values and length prefixes
+        for split in 0..=msg.len() {
+            let (head, tail) = msg.split_at(split);
+            assert_eq!(
+                sort_protobuf_message_ring(head, tail).unwrap(),
+                expected,
+                "split at {}",
+                split
+            );
+        }
+        assert!(sort_protobuf_message_ring(&[], &[]).unwrap().is_empty());
+        for (head, tail) in [
+            ([0x0F].as_slice(), [].as_slice()),
+            // Truncated varint value and fixed32 field
+            (&[0x08], &[0x96]),
+            (&[0x15, 1, 2], &[3]),
+            // Length beyond the end of the message
+            (&[0x0a, 0x03, b'a'], b"b"),
+        ] {
+            assert!(
+                sort_protobuf_message_ring(head, tail).is_err(),
+                "{:02x?} {:02x?}",
+                head,
+                tail
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::field::WireType;
+use crate::{parse_message, sort_protobuf_message, ParseError};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Expected wire types of message fields, for [`MessageSchema::validate_message`].
+#[derive(Debug, Clone, Default)]
+pub struct MessageSchema {
+    fields: HashMap<u32, WireType>,
+    strict: bool,
+}
+
+impl MessageSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, field_id: u32, wire_type: WireType) -> Self {
+        self.fields.insert(field_id, wire_type);
+        self
+    }
+
+    /// Whether fields missing in the schema are rejected (default: `false`).
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Checks every field of the given protobuf message against the schema.
+    pub fn validate_message(&self, msg: &[u8]) -> Result<(), SchemaError> {
+        for ck in &parse_message(msg)? {
+            let field_id = ck.id;
+            let actual = WireType::try_from(ck.wire_type)?;
+            match self.fields.get(&field_id) {
+                Some(&expected) if expected != actual => {
+                    return Err(SchemaError::WireTypeMismatch {
+                        field_id,
+                        expected,
+                        actual,
+                    })
+                }
+                None if self.strict => return Err(SchemaError::UnknownField(field_id)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum SchemaError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("field {0} is missing in the schema")]
+    UnknownField(u32),
+    #[error("field {field_id} has wire type {actual:?}, expected {expected:?}")]
+    WireTypeMismatch {
+        field_id: u32,
+        expected: WireType,
+        actual: WireType,
+    },
+}
+
+/// Error of [`sort_and_validate_message`]
+pub type SortValidateError = SchemaError;
+
+/// Validate the given protobuf message against the schema and sort its fields
+/// in the "canonical" order (by field ID).
+pub fn sort_and_validate_message<'a>(
+    msg: &'a [u8],
+    schema: &MessageSchema,
+) -> Result<Cow<'a, [u8]>, SortValidateError> {
+    schema.validate_message(msg)?;
+    Ok(sort_protobuf_message(msg)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_and_validate_message, MessageSchema, SchemaError};
+    use crate::WireType;
+
+    #[test]
+    fn test_validate_message() {
+        let schema = MessageSchema::new()
+            .field(1, WireType::Varint)
+            .field(3, WireType::LengthDelimited);
+        // Fields 3, 1, 2
+        let msg = [0x1a, 0x01, b'a', 0x08, 0x01, 0x10, 0x02];
+        schema.validate_message(&msg).unwrap();
+        assert!(matches!(
+            schema.clone().strict(true).validate_message(&msg),
+            Err(SchemaError::UnknownField(2))
+        ));
+        assert!(matches!(
+            schema.validate_message(&[0x0d, 0x01, 0x02, 0x03, 0x04]),
+            Err(SchemaError::WireTypeMismatch {
+                field_id: 1,
+                expected: WireType::Varint,
+                actual: WireType::Fixed32
+            })
+        ));
+        assert!(matches!(schema.validate_message(&[0x0F]), Err(SchemaError::Parse(_))));
+    }
+
+    #[test]
+    fn test_sort_and_validate_message() {
+        let schema = MessageSchema::new()
+            .field(1, WireType::Varint)
+            .field(2, WireType::Varint)
+            .strict(true);
+        assert_eq!(
+            sort_and_validate_message(&[0x10, 0x02, 0x08, 0x01], &schema).unwrap(),
+            [0x08, 0x01, 0x10, 0x02].as_slice()
+        );
+        assert!(sort_and_validate_message(&[0x18, 0x03, 0x08, 0x01], &schema).is_err());
+    }
+}
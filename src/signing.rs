@@ -0,0 +1,63 @@
+use crate::encode::encode_tag;
+use crate::{read_varint, sorted_chunks, write_varint, EncodeError, ParseError};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum PrepareError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+    #[error("protobuf message of {0} bytes is too large for 4-byte length prefix")]
+    MessageTooLarge(usize),
+}
+
+/// Convert the given protobuf message to a deterministic byte sequence suitable for signing.
+///
+/// Field IDs are validated, tags, varint values and length prefixes are re-encoded as minimal
+/// varints, fields are sorted by field ID, and the result is prefixed with its 4-byte big-endian
+/// length. Embedded messages are kept as is, since they can't be told apart from plain bytes.
+pub fn prepare_message_for_signing(msg: &[u8]) -> Result<Vec<u8>, PrepareError> {
+    let chunks = sorted_chunks(msg)?;
+    let mut result = vec![0; 4];
+    for ck in &chunks {
+        encode_tag(ck.id, ck.wire_type, &mut result)?;
+        let payload = ck.payload(msg);
+        match ck.wire_type {
+            0 => write_varint(read_varint(payload)?.0, &mut result),
+            2 => {
+                write_varint(payload.len() as u128, &mut result);
+                result.extend_from_slice(payload);
+            }
+            _ => result.extend_from_slice(payload),
+        }
+    }
+    let length = result.len() - 4;
+    let prefix = u32::try_from(length).map_err(|_| PrepareError::MessageTooLarge(length))?;
+    result[..4].copy_from_slice(&prefix.to_be_bytes());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare_message_for_signing, PrepareError};
+
+    #[test]
+    fn test_prepare_message_for_signing() {
+        // Field 3 with overlong length prefix, field 1 with overlong tag and value
+        let msg = [0x1a, 0x81, 0x00, b'a', 0x88, 0x00, 0x96, 0x81, 0x00];
+        let prepared = prepare_message_for_signing(&msg).unwrap();
+        assert_eq!(prepared, [0, 0, 0, 6, 0x08, 0x96, 0x01, 0x1a, 0x01, b'a']);
+        assert_eq!(prepare_message_for_signing(&prepared[4..]).unwrap(), prepared);
+        assert_eq!(prepare_message_for_signing(&[]).unwrap(), [0, 0, 0, 0]);
+        assert!(matches!(
+            prepare_message_for_signing(&[0x0F]),
+            Err(PrepareError::Parse(_))
+        ));
+        // Field 2^29 is out of range
+        assert!(matches!(
+            prepare_message_for_signing(&[0x80, 0x80, 0x80, 0x80, 0x10, 0x01]),
+            Err(PrepareError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::MAX_VARINT_LENGTH;
+use std::arch::x86_64::{__m128i, _mm_loadu_si128, _mm_movemask_epi8};
+
+/// Decode a varint using SSE2, returns `None` if the scalar path is needed to report an error
+#[inline]
+pub(crate) fn read_varint(bytes: &[u8]) -> Option<(u128, usize)> {
+    let mask = if bytes.len() >= 16 {
+        // SAFETY: `bytes` has at least 16 readable bytes (unaligned load is fine),
+        // and SSE2 is always available on x86-64
+        unsafe { _mm_movemask_epi8(_mm_loadu_si128(bytes.as_ptr() as *const __m128i)) }
+    } else {
+        // Short tail of the message: zero padding has no continuation bits
+        let mut buf = [0u8; 16];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        // SAFETY: `buf` has 16 readable bytes, and SSE2 is always available on x86-64
+        unsafe { _mm_movemask_epi8(_mm_loadu_si128(buf.as_ptr() as *const __m128i)) }
+    };
+    // Continuation bits of the 16 bytes, the first clear one marks the last byte of the varint
+    let len = (!mask).trailing_zeros() as usize + 1;
+    if len > MAX_VARINT_LENGTH || len > bytes.len() {
+        // Too long or truncated varint
+        return None;
+    }
+    let data = bytes[..len]
+        .iter()
+        .enumerate()
+        .fold(0, |data, (i, &byte)| data | (((byte & 0x7F) as u128) << (7 * i)));
+    Some((data, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_varint;
+
+    #[test]
+    fn test_read_varint_simd() {
+        let mut bytes = [0u8; 16];
+        bytes[0] = 0x54;
+        assert_eq!(read_varint(&bytes), Some((0x54, 1)));
+        bytes[0] = 0x90;
+        bytes[1] = 0x4e;
+        assert_eq!(read_varint(&bytes), Some((10000, 2)));
+        let mut max = [0xff; 16];
+        max[9] = 0x01;
+        assert_eq!(read_varint(&max), Some((u64::MAX as u128, 10)));
+        // Inputs shorter than 16 bytes
+        assert_eq!(read_varint(&[0x54; 15]), Some((0x54, 1)));
+        assert_eq!(read_varint(&[0x80, 0x10]), Some((2048, 2)));
+        // Errors are left for the scalar path
+        assert_eq!(read_varint(&[]), None);
+        assert_eq!(read_varint(&[0x80, 0x80]), None);
+        assert_eq!(read_varint(&[0xff; 16]), None);
+    }
+}
@@ -0,0 +1,78 @@
+//! [`proptest`] strategies generating structurally valid protobuf messages, for property tests.
+
+use crate::{write_varint, MAX_FIELD_ID};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Maximum number of fields in messages of [`valid_proto_message`] and [`sorted_proto_message`]
+const MAX_FIELDS: usize = 32;
+
+/// Field ID, tag and payload (with the length prefix for length-delimited fields) of a single field
+fn field() -> impl Strategy<Value = (u32, Vec<u8>)> {
+    // Mostly small IDs, so that repeated fields are common
+    let field_id = prop_oneof![4 => 1..=16u32, 1 => 1..=MAX_FIELD_ID as u32];
+    let payload = prop_oneof![
+        any::<u64>().prop_map(|value| (0, encode_varint(u128::from(value)))),
+        any::<[u8; 8]>().prop_map(|bytes| (1, bytes.to_vec())),
+        vec(any::<u8>(), 0..64).prop_map(|bytes| (2, [encode_varint(bytes.len() as u128), bytes].concat())),
+        any::<[u8; 4]>().prop_map(|bytes| (5, bytes.to_vec())),
+    ];
+    (field_id, payload).prop_map(|(field_id, (wire_type, payload))| {
+        let tag = encode_varint(u128::from(field_id) << 3 | wire_type);
+        (field_id, [tag, payload].concat())
+    })
+}
+
+fn encode_varint(value: u128) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_varint(value, &mut bytes);
+    bytes
+}
+
+fn concat_fields(fields: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+    fields.into_iter().flat_map(|(_, bytes)| bytes).collect()
+}
+
+/// Message of up to 32 fields of all wire types except groups, in random order.
+pub fn valid_proto_message() -> impl Strategy<Value = Vec<u8>> {
+    vec(field(), 0..=MAX_FIELDS).prop_map(concat_fields)
+}
+
+/// Same as [`valid_proto_message`], but with fields sorted in the "canonical" order (by field ID).
+pub fn sorted_proto_message() -> impl Strategy<Value = Vec<u8>> {
+    vec(field(), 0..=MAX_FIELDS).prop_map(|mut fields| {
+        fields.sort_by_key(|(field_id, _)| *field_id);
+        concat_fields(fields)
+    })
+}
+
+/// Message of exactly `n` fields in random order.
+pub fn proto_message_with_n_fields(n: usize) -> impl Strategy<Value = Vec<u8>> {
+    vec(field(), n).prop_map(concat_fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{proto_message_with_n_fields, sorted_proto_message, valid_proto_message};
+    use crate::{is_protobuf_message_sorted, parse_message, sort_protobuf_message};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_valid_proto_message(msg in valid_proto_message()) {
+            let sorted = sort_protobuf_message(&msg).unwrap();
+            prop_assert!(is_protobuf_message_sorted(&sorted).unwrap());
+            prop_assert_eq!(sorted.len(), msg.len());
+        }
+
+        #[test]
+        fn test_sorted_proto_message(msg in sorted_proto_message()) {
+            prop_assert!(is_protobuf_message_sorted(&msg).unwrap());
+        }
+
+        #[test]
+        fn test_proto_message_with_n_fields(msg in proto_message_with_n_fields(5)) {
+            prop_assert_eq!(parse_message(&msg).unwrap().len(), 5);
+        }
+    }
+}
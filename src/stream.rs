@@ -0,0 +1,81 @@
+use crate::{sort_protobuf_message, ParseError};
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SortStreamError {
+    #[error("protobuf message is larger than {limit} bytes")]
+    MessageTooLarge { limit: usize },
+    #[error("failed to read protobuf message stream")]
+    StreamError(#[source] Box<dyn StdError + Send + Sync>),
+    #[error(transparent)]
+    ParseError(#[from] ParseError),
+}
+
+/// Collect a protobuf message of at most `max_size` bytes from the stream of its parts,
+/// then sort its fields in the "canonical" order (by field ID).
+pub async fn sort_protobuf_message_stream<S, E>(mut stream: S, max_size: usize) -> Result<Bytes, SortStreamError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: Into<Box<dyn StdError + Send + Sync>>,
+{
+    let mut buf = BytesMut::new();
+    while let Some(item) = stream.next().await {
+        let part = item.map_err(|err| SortStreamError::StreamError(err.into()))?;
+        if part.len() > max_size - buf.len() {
+            return Err(SortStreamError::MessageTooLarge { limit: max_size });
+        }
+        buf.extend_from_slice(&part);
+    }
+    let sorted = match sort_protobuf_message(&buf)? {
+        Cow::Borrowed(_) => None,
+        Cow::Owned(sorted) => Some(sorted),
+    };
+    match sorted {
+        Some(sorted) => Ok(Bytes::from(sorted)),
+        None => Ok(buf.freeze()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_protobuf_message_stream, SortStreamError};
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use futures::stream;
+    use std::io;
+
+    fn parts(parts: &[&'static [u8]]) -> impl futures::Stream<Item = Result<Bytes, io::Error>> + Unpin {
+        stream::iter(
+            parts
+                .iter()
+                .map(|part| Ok(Bytes::from_static(part)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_sort_message_stream() {
+        let stream = parts(&[&[0x18, 0x03, 0x08], &[0x01], &[0x10, 0x02]]);
+        let sorted = block_on(sort_protobuf_message_stream(stream, 6)).unwrap();
+        assert_eq!(sorted, [0x08, 0x01, 0x10, 0x02, 0x18, 0x03].as_slice());
+        let stream = parts(&[]);
+        assert!(block_on(sort_protobuf_message_stream(stream, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sort_message_stream_errors() {
+        let stream = parts(&[&[0x18, 0x03, 0x08], &[0x01], &[0x10, 0x02]]);
+        let result = block_on(sort_protobuf_message_stream(stream, 5));
+        assert!(matches!(result, Err(SortStreamError::MessageTooLarge { limit: 5 })));
+        let stream = parts(&[&[0x0F]]);
+        let result = block_on(sort_protobuf_message_stream(stream, 5));
+        assert!(matches!(result, Err(SortStreamError::ParseError(_))));
+        let stream = stream::iter(vec![Err(io::Error::other("broken pipe"))]);
+        let result = block_on(sort_protobuf_message_stream(stream, 5));
+        assert!(matches!(result, Err(SortStreamError::StreamError(_))));
+    }
+}
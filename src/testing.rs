@@ -0,0 +1,83 @@
+use crate::{assemble, Chunk};
+use std::cmp::Reverse;
+use std::sync::OnceLock;
+
+/// Environment variable overriding the fields order of [`crate::sort_protobuf_message`]
+const SORT_ORDER_VAR: &str = "PROTOFIXER_SORT_ORDER";
+
+/// Seed of the "random" order, fixed so that test runs are reproducible
+const RANDOM_ORDER_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortOrder {
+    Ascending,
+    Descending,
+    Random,
+}
+
+impl SortOrder {
+    /// Unknown values fall back to the ascending order
+    fn parse(value: &str) -> SortOrder {
+        match value {
+            "descending" => SortOrder::Descending,
+            "random" => SortOrder::Random,
+            _ => SortOrder::Ascending,
+        }
+    }
+}
+
+/// Order set by `PROTOFIXER_SORT_ORDER`, read once per process
+fn sort_order() -> SortOrder {
+    static ORDER: OnceLock<SortOrder> = OnceLock::new();
+    *ORDER.get_or_init(|| std::env::var(SORT_ORDER_VAR).map_or(SortOrder::Ascending, |value| SortOrder::parse(&value)))
+}
+
+/// Reorder chunks as requested by `PROTOFIXER_SORT_ORDER`, to check that consumers don't depend
+/// on the fields order. Returns `None` for the default ascending order.
+pub(crate) fn sort_in_testing_order(chunks: &mut [Chunk], msg: &[u8]) -> Option<Vec<u8>> {
+    sort_in_order(sort_order(), chunks, msg)
+}
+
+/// Repeated fields always keep their relative order, since it's meaningful
+fn sort_in_order(order: SortOrder, chunks: &mut [Chunk], msg: &[u8]) -> Option<Vec<u8>> {
+    match order {
+        SortOrder::Ascending => return None,
+        SortOrder::Descending => chunks.sort_by_key(|ck| Reverse(ck.id)),
+        // Fields are ordered by a hash of their ID, which keeps occurrences of a field together
+        SortOrder::Random => {
+            chunks.sort_by_key(|ck| (u64::from(ck.id) ^ RANDOM_ORDER_SEED).wrapping_mul(RANDOM_ORDER_SEED))
+        }
+    }
+    Some(assemble(chunks, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_in_order, SortOrder};
+    use crate::parse_message;
+
+    #[test]
+    fn test_parse_sort_order() {
+        assert_eq!(SortOrder::parse("descending"), SortOrder::Descending);
+        assert_eq!(SortOrder::parse("random"), SortOrder::Random);
+        assert_eq!(SortOrder::parse("ascending"), SortOrder::Ascending);
+        assert_eq!(SortOrder::parse("sideways"), SortOrder::Ascending);
+    }
+
+    #[test]
+    fn test_sort_in_testing_order() {
+        // Fields 1, 3, 2, 3
+        let msg = [0x08, 0x01, 0x18, 0x03, 0x10, 0x02, 0x18, 0x04];
+        let sort = |order| sort_in_order(order, &mut parse_message(&msg).unwrap(), &msg);
+        assert!(sort(SortOrder::Ascending).is_none());
+        assert_eq!(
+            sort(SortOrder::Descending).unwrap(),
+            [0x18, 0x03, 0x18, 0x04, 0x10, 0x02, 0x08, 0x01]
+        );
+        let random = sort(SortOrder::Random).unwrap();
+        assert_eq!(random, sort(SortOrder::Random).unwrap());
+        let position = |bytes: [u8; 2]| random.windows(2).position(|w| w == bytes).unwrap();
+        assert_eq!(position([0x18, 0x03]) + 2, position([0x18, 0x04]));
+        assert_eq!(random.len(), msg.len());
+    }
+}
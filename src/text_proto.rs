@@ -0,0 +1,100 @@
+use crate::hex::{decode_hex, encode_hex};
+use crate::{parse_fields, read_varint, MessageBuilder, ParseError, WireType};
+use std::fmt::Write;
+
+/// Describe the given protobuf message in a compact text proto format, with fields sorted
+/// in the "canonical" order (by field ID), one per line, e.g.:
+/// `field_1: 150`, `field_2: 0x3f800000`, `field_3: "6869"`.
+///
+/// Varints are shown as unsigned decimals, fixed32 and fixed64 values as 8 and 16 hex digits,
+/// length-delimited payloads as quoted hex.
+pub fn message_to_text_proto(msg: &[u8]) -> Result<String, ParseError> {
+    let mut fields = parse_fields(msg)?;
+    fields.sort_by_key(|field| field.id());
+    let mut text = String::new();
+    for field in &fields {
+        let payload = field.payload();
+        let _ = write!(text, "field_{}: ", field.id());
+        let _ = match field.wire_type() {
+            WireType::Varint => write!(text, "{}", read_varint(payload)?.0),
+            WireType::Fixed32 => {
+                let value = u32::from_le_bytes(payload.try_into().expect("fixed32 field payload is 4 bytes"));
+                write!(text, "0x{:08x}", value)
+            }
+            WireType::Fixed64 => {
+                let value = u64::from_le_bytes(payload.try_into().expect("fixed64 field payload is 8 bytes"));
+                write!(text, "0x{:016x}", value)
+            }
+            WireType::LengthDelimited => write!(text, "\"{}\"", encode_hex(payload)),
+        };
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Encode a protobuf message from its [`message_to_text_proto`] description,
+/// with fields sorted in the "canonical" order (by field ID). Empty lines are ignored.
+pub fn text_proto_to_message(text: &str) -> Result<Vec<u8>, ParseError> {
+    let mut builder = MessageBuilder::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (name, value) = line.split_once(':').ok_or(ParseError)?;
+        let field_id = name.trim().strip_prefix("field_").ok_or(ParseError)?;
+        let field_id: u32 = field_id.parse().map_err(|_| ParseError)?;
+        let value = value.trim();
+        if let Some(hex) = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+            builder.push_bytes(field_id, &decode_hex(hex).ok_or(ParseError)?);
+        } else if let Some(hex) = value.strip_prefix("0x") {
+            let value = u64::from_str_radix(hex, 16).map_err(|_| ParseError)?;
+            match hex.len() {
+                8 => builder.push_field(field_id, WireType::Fixed32, &(value as u32).to_le_bytes()),
+                16 => builder.push_field(field_id, WireType::Fixed64, &value.to_le_bytes()),
+                _ => return Err(ParseError),
+            };
+        } else {
+            builder.push_varint(field_id, value.parse().map_err(|_| ParseError)?);
+        }
+    }
+    builder.build().map_err(|_| ParseError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{message_to_text_proto, text_proto_to_message};
+
+    #[test]
+    fn test_message_to_text_proto() {
+        // Fields 3 ("hi"), 2 (fixed32 1.0f), 1 (150), 4 (fixed64 1)
+        let msg = [
+            0x1a, 0x02, b'h', b'i', 0x15, 0x00, 0x00, 0x80, 0x3f, 0x08, 0x96, 0x01, 0x21, 0x01, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let text = message_to_text_proto(&msg).unwrap();
+        assert_eq!(
+            text,
+            "field_1: 150\nfield_2: 0x3f800000\nfield_3: \"6869\"\nfield_4: 0x0000000000000001\n"
+        );
+        let sorted = text_proto_to_message(&text).unwrap();
+        assert_eq!(sorted, crate::sort_protobuf_message(&msg).unwrap().as_ref());
+        assert_eq!(message_to_text_proto(&[]).unwrap(), "");
+        assert!(message_to_text_proto(&[0x0F]).is_err());
+    }
+
+    #[test]
+    fn test_text_proto_to_message() {
+        assert_eq!(
+            text_proto_to_message("field_3: \"\"\n\n  field_1 : 1  \n").unwrap(),
+            [0x08, 0x01, 0x1a, 0x00]
+        );
+        assert!(text_proto_to_message("").unwrap().is_empty());
+        for text in [
+            "field_1 1",
+            "id_1: 1",
+            "field_x: 1",
+            "field_1: -1",
+            "field_1: \"abc\"",
+            "field_1: 0x123",
+            "field_0: 1",
+        ] {
+            assert!(text_proto_to_message(text).is_err(), "{}", text);
+        }
+    }
+}
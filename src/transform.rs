@@ -0,0 +1,82 @@
+use crate::{sorted_chunks, write_varint, ParseError, WireType};
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("payload doesn't match wire type {wire_type:?} of field {field_id}")]
+    InvalidPayload { field_id: u32, wire_type: WireType },
+    #[error("failed to transform field payload")]
+    Other(#[source] Box<dyn StdError + Send + Sync>),
+}
+
+/// Transform field payloads of the given protobuf message, the result is sorted by field ID.
+///
+/// The closure receives the field ID, its wire type and the payload (without the tag and the
+/// length prefix), and returns the new payload, which must be valid for the wire type.
+/// The length prefix of length-delimited fields is updated accordingly.
+pub fn apply_field_transform<F>(msg: &[u8], transform: F) -> Result<Vec<u8>, TransformError>
+where
+    F: Fn(u32, WireType, &[u8]) -> Result<Cow<[u8]>, TransformError>,
+{
+    let chunks = sorted_chunks(msg)?;
+    let mut result = Vec::with_capacity(msg.len());
+    for ck in &chunks {
+        let field_id = ck.id;
+        let wire_type = WireType::try_from(ck.wire_type)?;
+        let payload = transform(field_id, wire_type, ck.payload(msg))?;
+        if !wire_type.is_valid_payload(&payload) {
+            return Err(TransformError::InvalidPayload { field_id, wire_type });
+        }
+        result.extend_from_slice(&ck.bytes(msg)[..ck.tag_length(msg)]);
+        if wire_type == WireType::LengthDelimited {
+            write_varint(payload.len() as u128, &mut result);
+        }
+        result.extend_from_slice(&payload);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_field_transform, TransformError};
+    use crate::WireType;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_apply_field_transform() {
+        // Fields 3 (varint), 1 (length-delimited), 2 (32-bit)
+        let msg = [0x18, 0x03, 0x0a, 0x02, 0xab, 0xcd, 0x15, 1, 2, 3, 4];
+        let result = apply_field_transform(&msg, |field_id, wire_type, payload| match (field_id, wire_type) {
+            (1, WireType::LengthDelimited) => Ok(Cow::Owned(b"hello".to_vec())),
+            (3, WireType::Varint) => Ok(Cow::Borrowed(&[0x96, 0x01])),
+            _ => Ok(Cow::Borrowed(payload)),
+        });
+        let expected = [
+            0x0a, 0x05, b'h', b'e', b'l', b'l', b'o', 0x15, 1, 2, 3, 4, 0x18, 0x96, 0x01,
+        ];
+        assert_eq!(result.unwrap(), expected);
+        let identity = apply_field_transform(&expected, |_, _, payload| Ok(Cow::Borrowed(payload)));
+        assert_eq!(identity.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_apply_field_transform_errors() {
+        let msg = [0x18, 0x03, 0x15, 1, 2, 3, 4];
+        let result = apply_field_transform(&msg, |_, _, _| Ok(Cow::Borrowed(&[1, 2])));
+        assert!(matches!(
+            result,
+            Err(TransformError::InvalidPayload {
+                field_id: 2,
+                wire_type: WireType::Fixed32
+            })
+        ));
+        let result = apply_field_transform(&msg, |_, _, _| Err(TransformError::Other("oops".into())));
+        assert!(matches!(result, Err(TransformError::Other(_))));
+        let result = apply_field_transform(&[0x0F], |_, _, payload| Ok(Cow::Borrowed(payload)));
+        assert!(matches!(result, Err(TransformError::Parse(_))));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{do_sort, is_sorted, parse_message, sort_protobuf_message, ParseError};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+#[error("payload of field {field_id} is too large ({payload_size} bytes)")]
+pub struct FieldTooLargeError {
+    pub field_id: u32,
+    pub payload_size: usize,
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum SizeLimitError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    FieldTooLarge(#[from] FieldTooLargeError),
+}
+
+/// Checks that no field payload in the given message is larger than `max_field_payload_bytes`.
+///
+/// Fails with the first field exceeding the limit.
+pub fn check_message_size_limit(msg: &[u8], max_field_payload_bytes: usize) -> Result<(), SizeLimitError> {
+    let chunks = parse_message(msg)?;
+    for ck in &chunks {
+        let payload_size = ck.length - ck.header_length;
+        if payload_size > max_field_payload_bytes {
+            let field_id = ck.id;
+            return Err(FieldTooLargeError { field_id, payload_size }.into());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum SortSizeError {
+    #[error("protobuf message of {actual} bytes exceeds the limit of {limit} bytes")]
+    MessageTooLarge { actual: usize, limit: usize },
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// rejecting messages longer than `max_bytes` without parsing them.
+pub fn sort_protobuf_message_limited_size(msg: &[u8], max_bytes: usize) -> Result<Cow<'_, [u8]>, SortSizeError> {
+    if msg.len() > max_bytes {
+        return Err(SortSizeError::MessageTooLarge {
+            actual: msg.len(),
+            limit: max_bytes,
+        });
+    }
+    Ok(sort_protobuf_message(msg)?)
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum FieldValidationError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("field {field_id} has invalid payload {payload:02x?}")]
+    InvalidField { field_id: u32, payload: Vec<u8> },
+}
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID), checking
+/// the payload of each field with its validator, if there is one for the field ID.
+///
+/// Payloads don't include the tag and the length prefix. Fails with the first invalid field
+/// in the message order.
+pub fn sort_protobuf_message_validated<'a>(
+    msg: &'a [u8],
+    validators: &HashMap<u32, fn(&[u8]) -> bool>,
+) -> Result<Cow<'a, [u8]>, FieldValidationError> {
+    let mut chunks = parse_message(msg)?;
+    for ck in &chunks {
+        let field_id = ck.id;
+        let payload = ck.payload(msg);
+        if validators.get(&field_id).is_some_and(|validator| !validator(payload)) {
+            let payload = payload.to_vec();
+            return Err(FieldValidationError::InvalidField { field_id, payload });
+        }
+    }
+    if is_sorted(&chunks) {
+        Ok(Cow::Borrowed(msg))
+    } else {
+        Ok(Cow::Owned(do_sort(&mut chunks, msg)))
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum EquivalenceError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("sorted protobuf message doesn't match the original: {0}")]
+    Mismatch(String),
+}
+
+/// Checks that sorting the given message is a pure reordering: the sorted message
+/// parses back to the same field IDs with the same number of occurrences.
+pub fn verify_sort_parse_equivalence(msg: &[u8]) -> Result<(), EquivalenceError> {
+    let sorted = sort_protobuf_message(msg)?;
+    check_same_fields(msg, &sorted)
+}
+
+fn check_same_fields(original: &[u8], sorted: &[u8]) -> Result<(), EquivalenceError> {
+    let count_fields = |msg: &[u8]| -> Result<BTreeMap<u32, usize>, ParseError> {
+        let mut counts = BTreeMap::new();
+        for ck in &parse_message(msg)? {
+            *counts.entry(ck.id).or_default() += 1;
+        }
+        Ok(counts)
+    };
+    let (original_counts, sorted_counts) = (count_fields(original)?, count_fields(sorted)?);
+    for (id, &count) in &original_counts {
+        match sorted_counts.get(id) {
+            None => return Err(EquivalenceError::Mismatch(format!("field {} is missing", id))),
+            Some(&sorted_count) if sorted_count != count => {
+                return Err(EquivalenceError::Mismatch(format!(
+                    "field {} occurs {} times instead of {}",
+                    id, sorted_count, count
+                )));
+            }
+            Some(_) => {}
+        }
+    }
+    if let Some(id) = sorted_counts.keys().find(|id| !original_counts.contains_key(id)) {
+        return Err(EquivalenceError::Mismatch(format!("unexpected field {}", id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_message_size_limit, check_same_fields, sort_protobuf_message_limited_size,
+        sort_protobuf_message_validated, verify_sort_parse_equivalence, EquivalenceError, FieldTooLargeError,
+        FieldValidationError, SizeLimitError, SortSizeError,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_sort_message_limited_size() {
+        // Fields 2, 1
+        let msg = [0x10, 0x02, 0x08, 0x01];
+        assert_eq!(
+            sort_protobuf_message_limited_size(&msg, 4).unwrap(),
+            [0x08, 0x01, 0x10, 0x02].as_slice()
+        );
+        assert!(matches!(
+            sort_protobuf_message_limited_size(&msg, 3),
+            Err(SortSizeError::MessageTooLarge { actual: 4, limit: 3 })
+        ));
+        assert!(matches!(
+            sort_protobuf_message_limited_size(&[0x0F], 3),
+            Err(SortSizeError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_message_size_limit() {
+        let msg = [0x08, 0x96, 0x01, 0x12, 0x03, 1, 2, 3, 0x1d, 1, 2, 3, 4];
+        assert!(check_message_size_limit(&msg, 4).is_ok());
+        assert!(matches!(
+            check_message_size_limit(&msg, 3),
+            Err(SizeLimitError::FieldTooLarge(FieldTooLargeError {
+                field_id: 3,
+                payload_size: 4
+            }))
+        ));
+        assert!(matches!(
+            check_message_size_limit(&msg, 2),
+            Err(SizeLimitError::FieldTooLarge(FieldTooLargeError {
+                field_id: 2,
+                payload_size: 3
+            }))
+        ));
+        assert!(check_message_size_limit(&[], 0).is_ok());
+        assert!(matches!(
+            check_message_size_limit(&[0x0F], 10),
+            Err(SizeLimitError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_sort_message_validated() {
+        let mut validators: HashMap<u32, fn(&[u8]) -> bool> = HashMap::new();
+        validators.insert(2, |payload| std::str::from_utf8(payload).is_ok());
+        // Fields 2 (string), 1
+        let msg = [0x12, 0x02, b'h', b'i', 0x08, 0x01];
+        assert_eq!(
+            sort_protobuf_message_validated(&msg, &validators).unwrap(),
+            [0x08, 0x01, 0x12, 0x02, b'h', b'i'].as_slice()
+        );
+        assert!(sort_protobuf_message_validated(&msg, &HashMap::new()).is_ok());
+        match sort_protobuf_message_validated(&[0x08, 0x01, 0x12, 0x01, 0xff], &validators) {
+            Err(FieldValidationError::InvalidField { field_id, payload }) => {
+                assert_eq!((field_id, payload), (2, vec![0xff]))
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(matches!(
+            sort_protobuf_message_validated(&[0x0F], &validators),
+            Err(FieldValidationError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_sort_parse_equivalence() {
+        assert!(verify_sort_parse_equivalence(&[0x18, 0x03, 0x08, 0x01, 0x18, 0x04]).is_ok());
+        assert!(verify_sort_parse_equivalence(&[]).is_ok());
+        assert!(matches!(
+            verify_sort_parse_equivalence(&[0x0F]),
+            Err(EquivalenceError::Parse(_))
+        ));
+        let mismatch = |original: &[u8], sorted: &[u8]| match check_same_fields(original, sorted) {
+            Err(EquivalenceError::Mismatch(description)) => description,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(mismatch(&[0x08, 0x01, 0x10, 0x02], &[0x08, 0x01]), "field 2 is missing");
+        assert_eq!(
+            mismatch(&[0x08, 0x01, 0x08, 0x02], &[0x08, 0x01]),
+            "field 1 occurs 1 times instead of 2"
+        );
+        assert_eq!(mismatch(&[0x08, 0x01], &[0x08, 0x01, 0x18, 0x03]), "unexpected field 3");
+    }
+}
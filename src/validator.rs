@@ -0,0 +1,213 @@
+use crate::{check_message_size_limit, is_sorted, parse_field, parse_message, read_varint};
+use crate::{FieldTooLargeError, ParseError, SizeLimitError};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum ValidationError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("protobuf message fields are not in canonical order")]
+    NotSorted,
+    #[error("protobuf message has {count} fields, more than {limit}")]
+    TooManyFields { count: usize, limit: usize },
+    #[error(transparent)]
+    FieldTooLarge(#[from] FieldTooLargeError),
+    #[error("field at offset {offset} has reserved ID 0")]
+    ZeroFieldId { offset: usize },
+    #[error("field {field_id} has a non-minimal varint encoding")]
+    NonMinimalVarint { field_id: u32 },
+    #[error("protobuf message failed {} validations: {0:?}", .0.len())]
+    Multiple(Vec<ValidationError>),
+}
+
+/// A check of a serialized protobuf message, see [`ComposedValidator`] for running many of them.
+///
+/// Validators are `Send + Sync`, so that a validation pipeline can be shared across threads.
+pub trait MessageValidator: Send + Sync {
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError>;
+}
+
+/// Checks that the fields are in the "canonical" order (by field ID).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SortedValidator;
+
+impl MessageValidator for SortedValidator {
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError> {
+        if is_sorted(&parse_message(msg)?) {
+            Ok(())
+        } else {
+            Err(ValidationError::NotSorted)
+        }
+    }
+}
+
+/// Checks that the message has at most the given number of fields.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldCountValidator(pub usize);
+
+impl MessageValidator for FieldCountValidator {
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError> {
+        let count = parse_message(msg)?.len();
+        if count > self.0 {
+            return Err(ValidationError::TooManyFields { count, limit: self.0 });
+        }
+        Ok(())
+    }
+}
+
+/// Checks that no field payload is larger than the given number of bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSizeValidator(pub usize);
+
+impl MessageValidator for FieldSizeValidator {
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError> {
+        check_message_size_limit(msg, self.0).map_err(|err| match err {
+            SizeLimitError::Parse(err) => err.into(),
+            SizeLimitError::FieldTooLarge(err) => err.into(),
+        })
+    }
+}
+
+/// Checks that no field has the reserved ID 0, telling this apart from other parse errors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoZeroFieldIdValidator;
+
+impl MessageValidator for NoZeroFieldIdValidator {
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError> {
+        let mut offset = 0;
+        while offset < msg.len() {
+            match parse_field(msg, offset) {
+                Ok(chunk) => offset += chunk.length,
+                Err(err) => {
+                    return match read_varint(&msg[offset..]) {
+                        Ok((key, _)) if key >> 3 == 0 => Err(ValidationError::ZeroFieldId { offset }),
+                        _ => Err(err.into()),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that tags, varint values and length prefixes are encoded with the minimal
+/// number of bytes, i.e. without trailing zero groups.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinimalVarintValidator;
+
+impl MessageValidator for MinimalVarintValidator {
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError> {
+        let is_minimal = |varint: &[u8]| varint.len() == 1 || varint.last() != Some(&0);
+        for ck in &parse_message(msg)? {
+            let field = ck.bytes(msg);
+            let (_, tag_length) = read_varint(field)?;
+            let minimal = match ck.wire_type {
+                0 | 2 => {
+                    let (_, value_length) = read_varint(&field[tag_length..])?;
+                    is_minimal(&field[tag_length..tag_length + value_length])
+                }
+                _ => true,
+            };
+            if !minimal || !is_minimal(&field[..tag_length]) {
+                return Err(ValidationError::NonMinimalVarint { field_id: ck.id });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs all the given validators and collects all their errors.
+#[derive(Default)]
+pub struct ComposedValidator {
+    validators: Vec<Box<dyn MessageValidator>>,
+}
+
+impl ComposedValidator {
+    pub fn new(validators: Vec<Box<dyn MessageValidator>>) -> Self {
+        ComposedValidator { validators }
+    }
+
+    /// Add one more validator to run after the existing ones.
+    pub fn and_then(mut self, validator: impl MessageValidator + 'static) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+}
+
+impl MessageValidator for ComposedValidator {
+    /// Fails with [`ValidationError::Multiple`] if more than one validator fails.
+    fn validate(&self, msg: &[u8]) -> Result<(), ValidationError> {
+        let mut errors: Vec<_> = self.validators.iter().filter_map(|v| v.validate(msg).err()).collect();
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(ValidationError::Multiple(errors)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ComposedValidator, FieldCountValidator, FieldSizeValidator, MessageValidator, MinimalVarintValidator,
+        NoZeroFieldIdValidator, SortedValidator, ValidationError,
+    };
+
+    #[test]
+    fn test_validators() {
+        // Fields 2, 1
+        let msg = [0x12, 0x03, 1, 2, 3, 0x08, 0x01];
+        assert!(matches!(
+            SortedValidator.validate(&msg),
+            Err(ValidationError::NotSorted)
+        ));
+        SortedValidator.validate(&[0x08, 0x01, 0x08, 0x00]).unwrap();
+        FieldCountValidator(2).validate(&msg).unwrap();
+        assert!(matches!(
+            FieldCountValidator(1).validate(&msg),
+            Err(ValidationError::TooManyFields { count: 2, limit: 1 })
+        ));
+        FieldSizeValidator(3).validate(&msg).unwrap();
+        assert!(matches!(
+            FieldSizeValidator(2).validate(&msg),
+            Err(ValidationError::FieldTooLarge(_))
+        ));
+        NoZeroFieldIdValidator.validate(&msg).unwrap();
+        assert!(matches!(
+            NoZeroFieldIdValidator.validate(&[0x08, 0x01, 0x00, 0x01]),
+            Err(ValidationError::ZeroFieldId { offset: 2 })
+        ));
+        assert!(matches!(
+            NoZeroFieldIdValidator.validate(&[0x08, 0x01, 0x0F]),
+            Err(ValidationError::Parse(_))
+        ));
+        MinimalVarintValidator.validate(&msg).unwrap();
+        // Overlong tag, varint value and length prefix
+        for msg in [&[0x88, 0x00, 0x01][..], &[0x08, 0x81, 0x00], &[0x12, 0x80, 0x00]] {
+            assert!(matches!(
+                MinimalVarintValidator.validate(msg),
+                Err(ValidationError::NonMinimalVarint { field_id: _ })
+            ));
+        }
+        for validator in [&SortedValidator as &dyn MessageValidator, &MinimalVarintValidator] {
+            assert!(matches!(validator.validate(&[0x0F]), Err(ValidationError::Parse(_))));
+        }
+    }
+
+    #[test]
+    fn test_composed_validator() {
+        let validator = ComposedValidator::new(vec![Box::new(SortedValidator)])
+            .and_then(FieldCountValidator(1))
+            .and_then(MinimalVarintValidator);
+        validator.validate(&[0x08, 0x01]).unwrap();
+        assert!(matches!(
+            validator.validate(&[0x08, 0x81, 0x00]),
+            Err(ValidationError::NonMinimalVarint { field_id: 1 })
+        ));
+        match validator.validate(&[0x10, 0x02, 0x08, 0x01]) {
+            Err(ValidationError::Multiple(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        ComposedValidator::default().validate(&[0x0F]).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{is_protobuf_message_sorted, sort_protobuf_message};
+use wasm_bindgen::prelude::*;
+
+/// Sort fields in the given protobuf message in the "canonical" order (by field ID),
+/// returning a `Uint8Array` to JavaScript, or throwing the error message.
+#[wasm_bindgen]
+pub fn sort_proto_message_wasm(msg: &[u8]) -> Result<Vec<u8>, JsValue> {
+    match sort_protobuf_message(msg) {
+        Ok(sorted) => Ok(sorted.into_owned()),
+        Err(err) => Err(JsValue::from_str(&err.to_string())),
+    }
+}
+
+/// Check if fields of the given protobuf message are sorted, `false` if it's malformed.
+#[wasm_bindgen]
+pub fn is_proto_message_sorted_wasm(msg: &[u8]) -> bool {
+    is_protobuf_message_sorted(msg).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_proto_message_sorted_wasm, sort_proto_message_wasm};
+
+    // `JsValue` errors can only be created on wasm targets
+    #[test]
+    fn test_sort_proto_message_wasm() {
+        assert_eq!(
+            sort_proto_message_wasm(&[0x10, 0x02, 0x08, 0x01]).unwrap(),
+            [0x08, 0x01, 0x10, 0x02]
+        );
+        assert!(sort_proto_message_wasm(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_proto_message_sorted_wasm() {
+        assert!(is_proto_message_sorted_wasm(&[0x08, 0x01, 0x10, 0x02]));
+        assert!(!is_proto_message_sorted_wasm(&[0x10, 0x02, 0x08, 0x01]));
+        assert!(!is_proto_message_sorted_wasm(&[0x0F]));
+    }
+}
@@ -0,0 +1,40 @@
+//! Concurrent sorting under the `loom` model checker, run with
+//! `RUSTFLAGS="--cfg loom" cargo test --test loom_tests --release`.
+//!
+//! The main sort functions share no state between calls. These tests fail if some shared state
+//! is added without synchronization that `loom` can see.
+
+#![cfg(loom)]
+
+use loom::sync::Arc;
+use loom::thread;
+use protofixer::{is_protobuf_message_sorted, sort_protobuf_message, sort_protobuf_message_inplace};
+
+#[test]
+fn test_concurrent_sort_different_messages() {
+    loom::model(|| {
+        let handles: Vec<_> = [[0x10, 0x02, 0x08, 0x01], [0x18, 0x03, 0x08, 0x01]]
+            .into_iter()
+            .map(|msg| thread::spawn(move || sort_protobuf_message(&msg).unwrap().into_owned()))
+            .collect();
+        let sorted: Vec<Vec<u8>> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(sorted, [[0x08, 0x01, 0x10, 0x02], [0x08, 0x01, 0x18, 0x03]]);
+    });
+}
+
+#[test]
+fn test_concurrent_sort_shared_message() {
+    loom::model(|| {
+        let msg = Arc::new(vec![0x10, 0x02, 0x08, 0x01]);
+        let sorter = {
+            let msg = msg.clone();
+            thread::spawn(move || {
+                let mut sorted = msg.to_vec();
+                assert!(sort_protobuf_message_inplace(&mut sorted).unwrap());
+                sorted
+            })
+        };
+        assert!(!is_protobuf_message_sorted(&msg).unwrap());
+        assert_eq!(sorter.join().unwrap(), [0x08, 0x01, 0x10, 0x02]);
+    });
+}